@@ -0,0 +1,315 @@
+use crate::customerror::CustomError;
+use crate::mandel::Buffer;
+use crate::mechanism::{Mechanism, RenderJob, RenderResult};
+use crate::palette::{rgb, Colouring};
+use crate::viewport::Viewport;
+use num::Complex;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+///An exponential zoom from the viewport of a job towards a target point.
+#[derive(Clone, Debug)]
+pub struct Zoom {
+    ///The center of the viewport of the last frame.
+    pub target: Complex<f64>,
+    ///The magnification of the last frame compared to the first one. A factor below 1 zooms out.
+    pub factor: f64,
+    ///The number of frames.
+    pub frames: usize,
+    ///The angle in degrees the viewport of the last frame is turned counterclockwise compared to
+    ///the first one. The frames between turn evenly.
+    pub rotation: f64,
+}
+
+impl Zoom {
+    ///Returns the magnification of a frame compared to the first one. It grows by the same
+    ///ratio from frame to frame, so the zoom looks steady.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The number of the frame, starting at 0.
+    pub fn scale(&self, index: usize) -> f64 {
+        if self.frames < 2 {
+            return 1.0;
+        }
+        self.factor.powf(index as f64 / (self.frames - 1) as f64)
+    }
+
+    ///Returns the job of a frame. The viewport shrinks by the scale of the frame while its center
+    ///moves from the center of the first frame to the target, by the same share as the width
+    ///moves to the width of the last frame. It turns by its share of the rotation.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The job of the first frame.
+    /// * `index` - The number of the frame, starting at 0.
+    pub fn frame(&self, job: &RenderJob, index: usize) -> RenderJob {
+        let start = job.viewport.center();
+        let scale = self.scale(index);
+        let progress = if self.factor == 1.0 {
+            index as f64 / (self.frames.max(2) - 1) as f64
+        } else {
+            (1.0 - 1.0 / scale) / (1.0 - 1.0 / self.factor)
+        };
+        let center = start + (self.target - start) * progress;
+        let turn = self.rotation * index as f64 / (self.frames.max(2) - 1) as f64;
+        RenderJob {
+            viewport: Viewport {
+                rotation: job.viewport.rotation + turn,
+                ..Viewport::from_size(
+                    center,
+                    job.viewport.width() / scale,
+                    job.viewport.height() / scale,
+                )
+            },
+            ..job.clone()
+        }
+    }
+}
+
+///Renders the frames of a zoom with mechanism one after another and passes each to sink as soon
+///as it is done, so only one frame is in memory at a time. Returns the time in ms it took to
+///compute all frames.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computes the frames.
+/// * `job` - The job of the first frame.
+/// * `zoom` - The zoom of the frames.
+/// * `sink` - Takes the number, the job and the result of a frame, for example to write it.
+pub fn render_zoom(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    zoom: &Zoom,
+    sink: &mut dyn FnMut(usize, &RenderJob, &RenderResult) -> Result<(), CustomError>,
+) -> Result<f64, CustomError> {
+    let mut time = 0.0;
+    for index in 0..zoom.frames {
+        let frame = zoom.frame(job, index);
+        let result = mechanism.run(&frame)?;
+        time += result.time;
+        sink(index, &frame, &result)?;
+    }
+    Ok(time)
+}
+
+///Writes the frames of an animation into one file, one frame after another.
+pub trait FrameWriter {
+    ///Appends a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A buffer holding the iteration count of one pixel per element.
+    fn write_frame(&mut self, pixels: &Buffer) -> Result<(), CustomError>;
+
+    ///Completes the file after the last frame.
+    fn finish(self: Box<Self>) -> Result<(), CustomError>;
+}
+
+///Returns true if the extension of path is the one of an animation format, y4m or gif.
+pub fn is_animation(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    matches!(extension.as_deref(), Some("y4m") | Some("gif"))
+}
+
+///Returns the writer for the animation format chosen by the extension of filename, see
+///is_animation.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the animation which will be created.
+/// * `bounds` - The dimensions of a frame.
+/// * `max_iter` - The iteration limit the frames are rendered with.
+/// * `colouring` - Maps the iteration counts to colours. If None, the frames are grayscale.
+/// * `fps` - The frames per second.
+pub fn frame_writer(
+    filename: &str,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+    fps: f64,
+) -> Result<Box<dyn FrameWriter>, CustomError> {
+    if filename.to_lowercase().ends_with(".gif") {
+        Ok(Box::new(GifWriter::new(
+            filename, bounds, max_iter, colouring, fps,
+        )?))
+    } else if filename.to_lowercase().ends_with(".y4m") {
+        Ok(Box::new(Y4mWriter::new(
+            filename, bounds, max_iter, colouring, fps,
+        )?))
+    } else {
+        Err(CustomError::InvalidArgument)
+    }
+}
+
+///Writes an uncompressed YUV4MPEG2 video, which can be played and encoded by standard tools
+///like ffmpeg. The colours are converted to BT.601 with limited range, and the chroma planes have
+///half the width and height (4:2:0). Alpha is dropped.
+pub struct Y4mWriter {
+    output: BufWriter<File>,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<Colouring>,
+}
+
+impl Y4mWriter {
+    ///Creates the file and writes the stream header, see frame_writer.
+    pub fn new(
+        filename: &str,
+        bounds: (usize, usize),
+        max_iter: u32,
+        colouring: Option<&Colouring>,
+        fps: f64,
+    ) -> Result<Self, CustomError> {
+        if bounds.0 == 0 || bounds.1 == 0 || !fps.is_finite() || fps <= 0.0 {
+            return Err(CustomError::InvalidArgument);
+        }
+        let mut output = BufWriter::new(File::create(filename)?);
+        //The frame rate is written as a fraction of whole numbers.
+        let rate = if fps.fract() == 0.0 {
+            format!("{}:1", fps)
+        } else {
+            format!("{}:1000", (fps * 1000.0).round())
+        };
+        output.write_fmt(format_args!(
+            "YUV4MPEG2 W{} H{} F{} Ip A1:1 C420jpeg\n",
+            bounds.0, bounds.1, rate
+        ))?;
+        Ok(Y4mWriter {
+            output,
+            bounds,
+            max_iter,
+            colouring: colouring.cloned(),
+        })
+    }
+}
+
+impl FrameWriter for Y4mWriter {
+    fn write_frame(&mut self, pixels: &Buffer) -> Result<(), CustomError> {
+        let (width, height) = self.bounds;
+        if pixels.len() != width * height {
+            return Err(CustomError::UnfittingArray);
+        }
+        let rgb = rgb(pixels, self.max_iter, self.colouring.as_ref());
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        let mut luma = Vec::with_capacity(width * height);
+        let mut u = vec![0.0; chroma_width * chroma_height];
+        let mut v = vec![0.0; chroma_width * chroma_height];
+        let mut count = vec![0.0; chroma_width * chroma_height];
+        for row in 0..height {
+            for column in 0..width {
+                let i = (row * width + column) * 3;
+                let (r, g, b) = (rgb[i] as f64, rgb[i + 1] as f64, rgb[i + 2] as f64);
+                luma.push((16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0).round() as u8);
+                //The chroma of a 2x2 block is the average of its pixels.
+                let c = (row / 2) * chroma_width + column / 2;
+                u[c] += 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+                v[c] += 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+                count[c] += 1.0;
+            }
+        }
+        self.output.write_all(b"FRAME\n")?;
+        self.output.write_all(&luma)?;
+        for plane in &[u, v] {
+            let plane: Vec<u8> = plane
+                .iter()
+                .zip(&count)
+                .map(|(sum, n)| (sum / n).round() as u8)
+                .collect();
+            self.output.write_all(&plane)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), CustomError> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+///Writes an animated GIF which repeats forever. Grayscale frames share a global palette of 256
+///grays, coloured frames get their own palette of at most 256 colours.
+pub struct GifWriter {
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<Colouring>,
+    ///The time a frame is shown in hundredths of a second.
+    delay: u16,
+}
+
+impl GifWriter {
+    ///Creates the file and writes the header, see frame_writer. The frame rate is rounded to
+    ///the hundredths of a second GIF supports.
+    pub fn new(
+        filename: &str,
+        bounds: (usize, usize),
+        max_iter: u32,
+        colouring: Option<&Colouring>,
+        fps: f64,
+    ) -> Result<Self, CustomError> {
+        if bounds.0 == 0
+            || bounds.1 == 0
+            || bounds.0 > 65535
+            || bounds.1 > 65535
+            || !fps.is_finite()
+            || fps <= 0.0
+        {
+            return Err(CustomError::InvalidArgument);
+        }
+        let grays: Vec<u8> = (0..=255u8).flat_map(|g| vec![g, g, g]).collect();
+        let palette: &[u8] = if colouring.is_none() { &grays } else { &[] };
+        let output = BufWriter::new(File::create(filename)?);
+        let mut encoder = gif::Encoder::new(output, bounds.0 as u16, bounds.1 as u16, palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(GifWriter {
+            encoder: Some(encoder),
+            bounds,
+            max_iter,
+            colouring: colouring.cloned(),
+            delay: (100.0 / fps).round().clamp(1.0, 65535.0) as u16,
+        })
+    }
+}
+
+impl FrameWriter for GifWriter {
+    fn write_frame(&mut self, pixels: &Buffer) -> Result<(), CustomError> {
+        let (width, height) = (self.bounds.0 as u16, self.bounds.1 as u16);
+        if pixels.len() != self.bounds.0 * self.bounds.1 {
+            return Err(CustomError::UnfittingArray);
+        }
+        let mut frame = match self.colouring {
+            //The gray of a pixel is its index in the global palette.
+            None => gif::Frame {
+                width,
+                height,
+                buffer: pixels.to_grayscale(self.max_iter).into(),
+                ..gif::Frame::default()
+            },
+            Some(ref colouring) => gif::Frame::from_rgb_speed(
+                width,
+                height,
+                &rgb(pixels, self.max_iter, Some(colouring)),
+                10,
+            ),
+        };
+        frame.delay = self.delay;
+        match self.encoder {
+            Some(ref mut encoder) => encoder.write_frame(&frame)?,
+            None => return Err(CustomError::InvalidArgument),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), CustomError> {
+        //into_inner writes the trailer.
+        if let Some(encoder) = self.encoder.take() {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -4,6 +4,8 @@ use std::fmt;
 pub enum CustomError {
     IoError(std::io::Error),
     ImageError(image::ImageError),
+    PngError(png::EncodingError),
+    GifError(gif::EncodingError),
     ParseIntError(std::num::ParseIntError),
     ParseFloatError(std::num::ParseFloatError),
     UnfittingArray,
@@ -17,6 +19,8 @@ impl fmt::Display for CustomError {
         match *self {
             CustomError::IoError(ref e) => write!(f, "{}", e),
             CustomError::ImageError(ref e) => write!(f, "{}", e),
+            CustomError::PngError(ref e) => write!(f, "{}", e),
+            CustomError::GifError(ref e) => write!(f, "{}", e),
             CustomError::ParseIntError(ref e) => write!(f, "{}", e),
             CustomError::ParseFloatError(ref e) => write!(f, "{}", e),
             CustomError::UnfittingArray => {
@@ -39,6 +43,8 @@ impl fmt::Debug for CustomError {
         match *self {
             CustomError::IoError(ref e) => write!(f, "{}", e),
             CustomError::ImageError(ref e) => write!(f, "{}", e),
+            CustomError::PngError(ref e) => write!(f, "{}", e),
+            CustomError::GifError(ref e) => write!(f, "{}", e),
             CustomError::ParseIntError(ref e) => write!(f, "{}", e),
             CustomError::ParseFloatError(ref e) => write!(f, "{}", e),
             CustomError::UnfittingArray => {
@@ -68,6 +74,18 @@ impl From<image::ImageError> for CustomError {
     }
 }
 
+impl From<png::EncodingError> for CustomError {
+    fn from(error: png::EncodingError) -> Self {
+        CustomError::PngError(error)
+    }
+}
+
+impl From<gif::EncodingError> for CustomError {
+    fn from(error: gif::EncodingError) -> Self {
+        CustomError::GifError(error)
+    }
+}
+
 impl From<std::num::ParseIntError> for CustomError {
     fn from(error: std::num::ParseIntError) -> Self {
         CustomError::ParseIntError(error)
@@ -0,0 +1,269 @@
+use crate::customerror::CustomError;
+use num::Complex;
+use std::fmt;
+use std::sync::Arc;
+
+///The squared distance below which an orbit counts as having returned to an earlier value, see
+///Formula::orbit.
+pub const PERIODICITY_TOLERANCE: f64 = 1e-20;
+
+///How the orbit of a point ended, see Formula::orbit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orbit {
+    ///The orbit escaped after a number of iterations with a value.
+    Escaped(u32, Complex<f64>),
+    ///The point is a member of the set. Holds the number of iterations which were computed.
+    Member(u32),
+}
+
+///A fractal formula which maps `z` to the next value of the orbit.
+///
+///Implement `step` for a new formula. The iteration loop is provided and compiled for every
+///formula separately, so `step` is inlined and only the call of `escape` is dynamic.
+pub trait Formula: Send + Sync + fmt::Debug {
+    ///The name which selects the formula on the command line.
+    fn name(&self) -> String;
+
+    ///Computes the next value of the orbit.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - The current value of the orbit.
+    /// * `c` - The parameter of the formula.
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64>;
+
+    ///The degree of the formula, which decides how fast an escaped orbit grows.
+    ///It is used to compute smooth iteration counts.
+    fn degree(&self) -> f64 {
+        2.0
+    }
+
+    ///Returns true if the parameter `c` is known to be a member of the set which starts the orbit
+    ///at z = 0, so it needs no iterations. The default knows no members.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The parameter of the formula.
+    fn known_member(&self, _c: Complex<f64>) -> bool {
+        false
+    }
+
+    ///Returns true if step is z² + c, which the vectorised kernel of simd::iterate computes with
+    ///the same result. The default is false, so the points are computed one by one.
+    fn vectorised(&self) -> bool {
+        false
+    }
+
+    ///Returns true if step is z² + c, which is the only formula a perturbation::Reference
+    ///iterates. The default is false.
+    fn quadratic(&self) -> bool {
+        false
+    }
+
+    ///Iterates the formula starting at `z` until the orbit leaves the circle of radius `bailout`
+    ///centered on the origin. Returns the number of iterations and the escaped value of the orbit
+    ///or `None` if `max_iter` was reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - The start value of the orbit.
+    /// * `c` - The parameter of the formula.
+    /// * `max_iter` - The maximum number of iterations.
+    /// * `bailout` - The radius of the circle the orbit has to leave.
+    fn escape(
+        &self,
+        mut z: Complex<f64>,
+        c: Complex<f64>,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Option<(u32, Complex<f64>)> {
+        let bailout_sqr = bailout * bailout;
+        for i in 0..max_iter {
+            z = self.step(z, c);
+            if z.norm_sqr() > bailout_sqr {
+                return Some((i, z));
+            }
+        }
+        None
+    }
+
+    ///Like escape, but also returns how many iterations a member of the set cost. If periodicity
+    ///is set, the orbit is checked for cycles with the method of Brent: it is compared with a
+    ///saved value, which is replaced after 1, 2, 4, 8, ... iterations. If it comes back to the
+    ///saved value within PERIODICITY_TOLERANCE, it is periodic and can't escape anymore.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - The start value of the orbit.
+    /// * `c` - The parameter of the formula.
+    /// * `max_iter` - The maximum number of iterations.
+    /// * `bailout` - The radius of the circle the orbit has to leave.
+    /// * `periodicity` - Decides whether the orbit is checked for cycles.
+    fn orbit(
+        &self,
+        mut z: Complex<f64>,
+        c: Complex<f64>,
+        max_iter: u32,
+        bailout: f64,
+        periodicity: bool,
+    ) -> Orbit {
+        if !periodicity {
+            return match self.escape(z, c, max_iter, bailout) {
+                Some((i, z)) => Orbit::Escaped(i, z),
+                None => Orbit::Member(max_iter),
+            };
+        }
+        let bailout_sqr = bailout * bailout;
+        let mut saved = z;
+        let (mut steps, mut limit) = (0, 1);
+        for i in 0..max_iter {
+            z = self.step(z, c);
+            if z.norm_sqr() > bailout_sqr {
+                return Orbit::Escaped(i, z);
+            }
+            if (z - saved).norm_sqr() < PERIODICITY_TOLERANCE {
+                return Orbit::Member(i + 1);
+            }
+            steps += 1;
+            if steps == limit {
+                saved = z;
+                steps = 0;
+                limit *= 2;
+            }
+        }
+        Orbit::Member(max_iter)
+    }
+}
+
+///The Mandelbrot formula z^2 + c.
+#[derive(Debug)]
+pub struct Mandelbrot;
+
+impl Formula for Mandelbrot {
+    fn name(&self) -> String {
+        "mandelbrot".to_string()
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z * z + c
+    }
+
+    ///The members inside the main cardioid and the period-2 bulb, the two largest components.
+    fn known_member(&self, c: Complex<f64>) -> bool {
+        let x = c.re - 0.25;
+        let y2 = c.im * c.im;
+        let q = x * x + y2;
+        q * (q + x) <= 0.25 * y2 || (c.re + 1.0) * (c.re + 1.0) + y2 <= 0.0625
+    }
+
+    fn vectorised(&self) -> bool {
+        true
+    }
+
+    fn quadratic(&self) -> bool {
+        true
+    }
+}
+
+///The Multibrot formula z^d + c for an integer exponent d.
+#[derive(Debug)]
+pub struct Multibrot(pub i32);
+
+impl Formula for Multibrot {
+    fn name(&self) -> String {
+        format!("multibrot:{}", self.0)
+    }
+
+    fn degree(&self) -> f64 {
+        self.0 as f64
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z.powi(self.0) + c
+    }
+
+    fn quadratic(&self) -> bool {
+        self.0 == 2
+    }
+}
+
+///The Multibrot formula z^d + c for a real exponent d.
+#[derive(Debug)]
+pub struct MultibrotReal(pub f64);
+
+impl Formula for MultibrotReal {
+    fn name(&self) -> String {
+        format!("multibrot:{:?}", self.0)
+    }
+
+    fn degree(&self) -> f64 {
+        self.0
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z.powf(self.0) + c
+    }
+
+    fn quadratic(&self) -> bool {
+        self.0 == 2.0
+    }
+}
+
+///The Burning Ship formula (|re(z)| + i|im(z)|)^2 + c.
+#[derive(Debug)]
+pub struct BurningShip;
+
+impl Formula for BurningShip {
+    fn name(&self) -> String {
+        "burningship".to_string()
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = Complex {
+            re: z.re.abs(),
+            im: z.im.abs(),
+        };
+        z * z + c
+    }
+}
+
+///The Tricorn formula conj(z)^2 + c.
+#[derive(Debug)]
+pub struct Tricorn;
+
+impl Formula for Tricorn {
+    fn name(&self) -> String {
+        "tricorn".to_string()
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = z.conj();
+        z * z + c
+    }
+}
+
+///Returns the formula for a name given on the command line.
+///
+/// # Arguments
+///
+/// * `name` - One of mandelbrot, burningship, tricorn or multibrot:d where d is an integer or real exponent greater than 1.
+pub fn parse_formula(name: &str) -> Result<Arc<dyn Formula>, CustomError> {
+    match name {
+        "mandelbrot" => Ok(Arc::new(Mandelbrot)),
+        "burningship" => Ok(Arc::new(BurningShip)),
+        "tricorn" => Ok(Arc::new(Tricorn)),
+        _ if name.starts_with("multibrot:") => {
+            let exponent = &name["multibrot:".len()..];
+            let formula: Arc<dyn Formula> = match exponent.parse::<i32>() {
+                Ok(d) => Arc::new(Multibrot(d)),
+                Err(_) => Arc::new(MultibrotReal(exponent.parse::<f64>()?)),
+            };
+            //The escape and the smooth count, which divides by ln(d), need d > 1.
+            match exponent.parse::<f64>()? {
+                d if d.is_finite() && d > 1.0 => Ok(formula),
+                _ => Err(CustomError::InvalidArgument),
+            }
+        }
+        _ => Err(CustomError::InvalidArgument),
+    }
+}
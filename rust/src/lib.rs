@@ -1,9 +1,20 @@
 #[macro_use]
 extern crate clap;
-mod customerror;
+pub mod animation;
+pub mod customerror;
+pub mod formula;
 pub mod mandel;
+pub mod marianisilver;
+pub mod mechanism;
+pub mod output;
+pub mod palette;
 pub mod parseargs;
+pub mod perturbation;
+pub mod simd;
+pub mod terminal;
+pub mod tiles;
 pub mod time;
+pub mod viewport;
 pub mod wcrossbeam;
 pub mod wrayon;
 pub mod wscopedthreadpool;
@@ -1,10 +1,42 @@
+use mandelbrot::animation::{frame_writer, is_animation, render_zoom};
+use mandelbrot::customerror::CustomError;
+use mandelbrot::mandel::{write_npy, Kernel};
+use mandelbrot::mechanism::{
+    measure_workload, CostReport, Mechanism, Registry, RenderJob, RenderResult, Validation,
+};
+use mandelbrot::output::{expand_frame, expand_template, per_frame, per_mechanism, ImageOutput};
 use mandelbrot::parseargs::parse_arguments;
-use mandelbrot::wcrossbeam::{measure_workload_crossbeam, time_crossbeam};
-use mandelbrot::wrayon::{measure_workload_rayon, time_rayon};
-use mandelbrot::wscopedthreadpool::{measure_workload_scoped_threadpool, time_scoped_threadpool};
-use mandelbrot::wthreads::{measure_workload_threads, time_threads};
-use mandelbrot::wthreadsunsafe::{measure_workload_threads_unsafe, time_threads_unsafe};
+use mandelbrot::perturbation::Reference;
+use mandelbrot::terminal::{explore, preview, terminal_size, Explorer};
+use mandelbrot::tiles::write_tiles;
 use std::process::exit;
+use std::sync::Arc;
+
+///Writes the image and the NumPy array of a result if they are requested.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computed the image.
+/// * `job` - The job of the image.
+/// * `result` - The computed pixels and the time it took.
+/// * `output` - Decides where and how the image is written.
+/// * `dump_npy` - The path of the NumPy array, which may hold placeholders.
+fn write_result(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    result: &RenderResult,
+    output: Option<&ImageOutput>,
+    dump_npy: Option<&str>,
+) -> Result<(), CustomError> {
+    if let Some(output) = output {
+        output.write(mechanism, job, result)?;
+    }
+    if let Some(template) = dump_npy {
+        let filename = expand_template(template, mechanism, job);
+        write_npy(&filename, &result.pixels, job.bounds)?;
+    }
+    Ok(())
+}
 
 ///A basic tui with error handling
 fn main() {
@@ -16,152 +48,291 @@ fn main() {
         }
     };
 
-    if args.mechanism.eq("threads") || args.mechanism.eq("th") || args.mechanism.eq("all") {
-        if args.measure {
-            match measure_workload_threads(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.draw,
-            ) {
-                Ok(_) => println!("Workload measure with threading complete!"),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    exit(1);
-                }
-            }
-        } else {
-            match time_threads(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.threads,
-                args.draw,
-            ) {
-                Ok(time) => println!("Time with threading: {}ms", time),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    exit(1);
-                }
+    let registry = Registry::default();
+    let mechanisms = match registry.select(&args.mechanism) {
+        Ok(m) => m,
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    //The orbit of the center of a deep zoom is computed once for all mechanisms.
+    let reference = args.deep.map(|center| {
+        Arc::new(Reference::new(
+            center,
+            args.julia,
+            args.max_iter,
+            args.bailout,
+        ))
+    });
+
+    let job = RenderJob {
+        bounds: args.bounds,
+        viewport: args.viewport,
+        threads: args.threads,
+        rows_per_band: args.rows_per_band,
+        kernel: Kernel {
+            max_iter: args.max_iter,
+            julia: args.julia,
+            formula: args.formula,
+            bailout: args.bailout,
+            smooth: args.smooth,
+            shortcut: args.shortcut,
+            periodicity: args.periodicity,
+            simd: args.simd,
+            reference,
+        },
+        strategy: args.strategy,
+    };
+
+    if let Some(ref reference) = job.kernel.reference {
+        println!(
+            "Reference orbit with {} iterations at {},{}",
+            reference.iterations(),
+            reference.center.0,
+            reference.center.1
+        );
+    }
+
+    let output = if args.draw {
+        let mut output = ImageOutput::new(&args.output);
+        output.colouring = args.colouring.clone();
+        output.depth = args.depth;
+        if mechanisms.len() > 1 {
+            output.per_mechanism();
+        }
+        if args.zoom.is_some() && !is_animation(&output.template) {
+            output.template = per_frame(&output.template);
+        }
+        Some(output)
+    } else {
+        None
+    };
+
+    let dump_npy = match args.dump_npy {
+        Some(ref template) if mechanisms.len() > 1 => Some(per_mechanism(template)),
+        Some(ref template) => Some(template.to_string()),
+        None => None,
+    };
+    let dump_npy = match dump_npy {
+        Some(template) if args.zoom.is_some() => Some(per_frame(&template)),
+        dump_npy => dump_npy,
+    };
+
+    let tiles = match args.tiles {
+        Some(ref template) if mechanisms.len() > 1 => Some(per_mechanism(template)),
+        Some(ref template) => Some(template.to_string()),
+        None => None,
+    };
+
+    if args.explore {
+        if mechanisms.len() != 1 {
+            println!("{}", CustomError::InvalidArgument);
+            exit(1);
+        }
+        let mut explorer = Explorer::new(job, args.colouring);
+        match explore(mechanisms[0], &mut explorer) {
+            Ok(_) => println!("{}", explorer.job.viewport.arguments()),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
             }
         }
+        return;
+    }
+
+    //The iterations don't depend on the mechanism, so they are counted once.
+    let report = if args.cost {
+        let report = CostReport::new(&job);
+        println!(
+            "Iterations: {} plain, {} with the shortcuts, {:.1}% of the saved iterations are on the {} members of the set",
+            report.plain.total(),
+            report.cost.total(),
+            report.interior_share() * 100.0,
+            report.cost.members
+        );
+        Some(report)
+    } else {
+        None
+    };
+
+    if let Some(lanes) = job.kernel.simd {
+        match job.kernel.lanes() {
+            Some(_) => println!(
+                "Vectorised with {}, {} points per group",
+                lanes.name(),
+                lanes.width()
+            ),
+            None => println!("The vectorised kernel only computes whole counts of the mandelbrot formula without --periodicity, the points are computed one by one"),
+        }
+    }
+
+    //The shortcut depends only on the viewport, so the fraction is the same for every mechanism.
+    if args.shortcut {
+        println!(
+            "Shortcut for {:.2}% of the pixels",
+            job.shortcut_fraction() * 100.0
+        );
     }
 
-    if args.mechanism.eq("threadsunsafe") || args.mechanism.eq("tu") || args.mechanism.eq("all") {
+    //Validation reports every mechanism before it fails.
+    let mut failed = false;
+
+    for mechanism in mechanisms {
         if args.measure {
-            match measure_workload_threads_unsafe(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.draw,
-            ) {
-                Ok(_) => println!("Workload measure with threading unsafe complete!"),
+            match measure_workload(mechanism, &job, output.as_ref()) {
+                Ok(_) => println!("Workload measure with {} complete!", mechanism.label()),
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        } else {
-            match time_threads_unsafe(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.threads,
-                args.draw,
-            ) {
-                Ok(time) => println!("Time with threading unsafe: {}ms", time),
+        } else if let Some(ref report) = report {
+            match CostReport::measure(mechanism, &job) {
+                Ok((plain, time)) => println!(
+                    "Cost with {}: {}ms plain, {}ms with the shortcuts, speedup {:.2}, about {:.1}ms saved on members of the set",
+                    mechanism.label(),
+                    plain,
+                    time,
+                    plain / time,
+                    (plain - time) * report.interior_share()
+                ),
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        }
-    }
-
-    if args.mechanism.eq("crossbeam") || args.mechanism.eq("cb") || args.mechanism.eq("all") {
-        if args.measure {
-            match measure_workload_crossbeam(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.draw,
-            ) {
-                Ok(_) => println!("Workload measure with crossbeam complete!"),
+        } else if args.validate {
+            match Validation::run(mechanism, &job) {
+                Ok(validation) => {
+                    println!(
+                        "Validation with {}: {} of {} pixels differ from brute force, {}ms with {}, {}ms brute force",
+                        mechanism.label(),
+                        validation.differing,
+                        validation.pixels,
+                        validation.time,
+                        validation.strategy.name(),
+                        validation.brute_force
+                    );
+                    failed |= !validation.passed();
+                }
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        } else {
-            match time_crossbeam(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.threads,
-                args.draw,
+        } else if args.preview {
+            //One row of the terminal is left for the time.
+            let (columns, rows) = terminal_size();
+            match preview(
+                mechanism,
+                &job,
+                (columns, rows.saturating_sub(1).max(1)),
+                args.colouring.as_ref(),
             ) {
-                Ok(time) => println!("Time with crossbeam: {}ms", time),
+                Ok((ansi, result)) => {
+                    print!("{}", ansi);
+                    println!("Time with {}: {}ms", mechanism.label(), result.time)
+                }
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        }
-    }
-
-    if args.mechanism.eq("scoped_threadpool") || args.mechanism.eq("st") || args.mechanism.eq("all")
-    {
-        if args.measure {
-            match measure_workload_scoped_threadpool(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.threads,
-                args.draw,
-            ) {
-                Ok(_) => println!("Workload measure with scoped_threadpool complete!"),
+        } else if let Some(ref template) = tiles {
+            let dir = expand_template(template, mechanism, &job);
+            match write_tiles(mechanism, &job, &dir, args.colouring.as_ref()) {
+                Ok(time) => println!("Time with {}: {}ms", mechanism.label(), time),
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        } else {
-            match time_scoped_threadpool(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.rows_per_band,
-                args.threads,
-                args.draw,
-            ) {
-                Ok(time) => println!("Time with scoped_threadpool: {}ms", time),
+        } else if let Some(ref zoom) = args.zoom {
+            //An animation format gets all frames, otherwise every frame is written on its own.
+            let mut animation = match output {
+                Some(ref o) if is_animation(&o.template) => {
+                    match frame_writer(
+                        &o.path(mechanism, &job),
+                        job.bounds,
+                        job.kernel.max_iter,
+                        o.colouring.as_ref(),
+                        args.fps,
+                    ) {
+                        Ok(writer) => Some(writer),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            exit(1);
+                        }
+                    }
+                }
+                _ => None,
+            };
+            let mut write_frame = |index: usize, frame: &RenderJob, result: &RenderResult| {
+                let frame_npy = dump_npy.as_ref().map(|t| expand_frame(t, index));
+                match animation {
+                    Some(ref mut writer) => {
+                        writer.write_frame(&result.pixels)?;
+                        write_result(mechanism, frame, result, None, frame_npy.as_deref())
+                    }
+                    None => {
+                        let frame_output = output.as_ref().map(|o| ImageOutput {
+                            template: expand_frame(&o.template, index),
+                            ..o.clone()
+                        });
+                        write_result(
+                            mechanism,
+                            frame,
+                            result,
+                            frame_output.as_ref(),
+                            frame_npy.as_deref(),
+                        )
+                    }
+                }
+            };
+            let result = render_zoom(mechanism, &job, zoom, &mut write_frame);
+            let result = match animation {
+                Some(writer) => result.and_then(|time| writer.finish().map(|_| time)),
+                None => result,
+            };
+            match result {
+                Ok(time) => println!(
+                    "Time with {}: {}ms for {} frames, {} frames per second",
+                    mechanism.label(),
+                    time,
+                    zoom.frames,
+                    zoom.frames as f64 / time * 1000.0
+                ),
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
-        }
-    }
-
-    if args.mechanism.eq("rayon") || args.mechanism.eq("ra") || args.mechanism.eq("all") {
-        if args.measure {
-            match measure_workload_rayon(args.bounds, args.upper_left, args.lower_right, args.draw)
-            {
-                Ok(_) => println!("Workload measure with rayon complete!"),
+        } else if args.stream {
+            //--stream is rejected together with --drawoff, so there is an output.
+            match output.as_ref().unwrap().stream(mechanism, &job) {
+                Ok(time) => println!("Time with {}: {}ms", mechanism.label(), time),
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
                 }
             }
         } else {
-            match time_rayon(
-                args.bounds,
-                args.upper_left,
-                args.lower_right,
-                args.rows_per_band,
-                args.draw,
-            ) {
-                Ok(time) => println!("Time with rayon: {}ms", time),
+            match mechanism.run(&job) {
+                Ok(result) => {
+                    if let Err(e) = write_result(
+                        mechanism,
+                        &job,
+                        &result,
+                        output.as_ref(),
+                        dump_npy.as_deref(),
+                    ) {
+                        eprintln!("{}", e);
+                        exit(1);
+                    }
+                    println!("Time with {}: {}ms", mechanism.label(), result.time)
+                }
                 Err(e) => {
                     eprintln!("{}", e);
                     exit(1);
@@ -169,4 +340,8 @@ fn main() {
             }
         }
     }
+
+    if failed {
+        exit(1);
+    }
 }
@@ -1,123 +1,431 @@
 use crate::customerror::CustomError;
+use crate::formula::{Formula, Mandelbrot, Orbit};
+use crate::palette::{rgb, Colouring, Mapping};
+use crate::perturbation::Reference;
+use crate::simd::{iterate, Lanes};
+use crate::viewport::Viewport;
 use crate::wthreadsunsafe::WrappedUnsafeCell;
-use image::png::PNGEncoder;
 use image::ColorType;
 use num::Complex;
+use png::text_metadata::{ITXtChunk, TEXtChunk};
 use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-/// Try to determine if `c` is in the Mandelbrot set, using at most 256
-/// iterations due to the grayscale color spectrum of the Png Writer.
-///
-/// If `c` is not a member, return `Some(i)`, where `i` is the number of
-/// iterations it took for `c` to leave the circle of radius two centered on the
-/// origin. If `c` seems to be a member (more precisely, if we reached the
-/// iteration limit without being able to prove that `c` is not a member),
-/// return `None`.
+///The parameters of the iteration which decides the value of a single pixel.
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    ///The maximum number of iterations per pixel.
+    pub max_iter: u32,
+    ///If set, the Julia set of this parameter is rendered instead of the Mandelbrot set.
+    pub julia: Option<Complex<f64>>,
+    ///The formula which is iterated.
+    pub formula: Arc<dyn Formula>,
+    ///The radius of the circle centered on the origin an orbit has to leave to escape.
+    pub bailout: f64,
+    ///Decides whether the pixels hold smooth iteration counts instead of whole numbers.
+    pub smooth: bool,
+    ///Decides whether points the formula knows as members skip the iteration, see
+    ///Formula::known_member. Julia sets never take the shortcut.
+    pub shortcut: bool,
+    ///Decides whether orbits are checked for cycles, which stops the iteration of most members
+    ///early, see Formula::orbit.
+    pub periodicity: bool,
+    ///If set, rows of whole iteration counts are computed by the vectorised kernel with these
+    ///lanes, see Kernel::lanes.
+    pub simd: Option<Lanes>,
+    ///If set, the points are offsets from the center of this reference orbit and are iterated
+    ///with perturbation, see Reference. It only knows the formula z² + c and neither the shortcut
+    ///nor periodicity.
+    pub reference: Option<Arc<Reference>>,
+}
 
-/// # Arguments
-///
-/// * `c` - A complex number to be determined if it is in the mandelbrot set or not.
-fn escape_mandel_iterations(c: Complex<f64>) -> Option<u32> {
-    let mut z = Complex { re: 0.0, im: 0.0 };
-    for i in 0..256 {
-        z = z * z + c;
-        if z.norm_sqr() > 4.0 {
-            return Some(i);
+impl Kernel {
+    ///Returns a Kernel for the Mandelbrot set.
+    pub fn new(max_iter: u32) -> Self {
+        Kernel {
+            max_iter,
+            julia: None,
+            formula: Arc::new(Mandelbrot),
+            bailout: 2.0,
+            smooth: false,
+            shortcut: false,
+            periodicity: false,
+            simd: None,
+            reference: None,
+        }
+    }
+
+    /// Try to determine if `point` is in the set of the formula, using at most `max_iter`
+    /// iterations.
+    ///
+    /// For the Mandelbrot set `point` is the parameter `c` and the iteration starts at z = 0.
+    /// For a Julia set `point` is the start value of `z` and `c` is the fixed julia parameter.
+    ///
+    /// If `point` is not a member, return `Some(i)`, where `i` is the number of
+    /// iterations it took for `z` to leave the circle of radius `bailout` centered on the
+    /// origin. If `point` seems to be a member (more precisely, if we reached the
+    /// iteration limit without being able to prove that `point` is not a member),
+    /// return `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A complex number to be determined if it is in the set or not.
+    pub fn escape_iterations(&self, point: Complex<f64>) -> Option<u32> {
+        self.escape(point).map(|(i, _)| i)
+    }
+
+    ///Like escape_iterations, but also returns the value of the orbit after it escaped.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A complex number to be determined if it is in the set or not.
+    pub fn escape(&self, point: Complex<f64>) -> Option<(u32, Complex<f64>)> {
+        if let Some(ref reference) = self.reference {
+            return reference.escape(point, self.max_iter, self.bailout).0;
+        }
+        if self.takes_shortcut(point) {
+            return None;
+        }
+        let (z, c) = self.start(point);
+        if self.periodicity {
+            match self.formula.orbit(z, c, self.max_iter, self.bailout, true) {
+                Orbit::Escaped(i, z) => Some((i, z)),
+                Orbit::Member(_) => None,
+            }
+        } else {
+            self.formula.escape(z, c, self.max_iter, self.bailout)
+        }
+    }
+
+    ///Returns how the orbit of `point` ends and how many iterations it costs with the shortcut and
+    ///the periodicity detection of the kernel. A member which takes the shortcut costs none. With a
+    ///reference orbit every member costs `max_iter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A complex number to be determined if it is in the set or not.
+    pub fn orbit(&self, point: Complex<f64>) -> Orbit {
+        if self.reference.is_some() {
+            return match self.escape(point) {
+                Some((i, z)) => Orbit::Escaped(i, z),
+                None => Orbit::Member(self.max_iter),
+            };
+        }
+        if self.takes_shortcut(point) {
+            return Orbit::Member(0);
+        }
+        let (z, c) = self.start(point);
+        self.formula
+            .orbit(z, c, self.max_iter, self.bailout, self.periodicity)
+    }
+
+    ///Returns the start value of the orbit and the parameter of the formula for `point`.
+    fn start(&self, point: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+        match self.julia {
+            Some(c) => (point, c),
+            None => (Complex { re: 0.0, im: 0.0 }, point),
+        }
+    }
+
+    ///Returns true if `point` is a member of the set without iterating, see shortcut.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A complex number to be determined if it is in the set or not.
+    pub fn takes_shortcut(&self, point: Complex<f64>) -> bool {
+        self.shortcut
+            && self.julia.is_none()
+            && self.reference.is_none()
+            && self.formula.known_member(point)
+    }
+
+    ///Returns the lanes of the vectorised kernel which computes rows, see simd::iterate. It is only
+    ///used for whole counts of the formula z² + c without periodicity and perturbation, otherwise
+    ///None is returned and the points are computed one by one.
+    pub fn lanes(&self) -> Option<Lanes> {
+        if self.smooth || self.periodicity || self.reference.is_some() || !self.formula.vectorised()
+        {
+            return None;
+        }
+        self.simd
+    }
+
+    ///Computes the iteration counts of a row of points like iterations, with the vectorised
+    ///kernel if the kernel has lanes. Points which take the shortcut are left out of the groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - The points of the row.
+    /// * `counts` - A buffer which receives one iteration count per point.
+    pub fn row_iterations(&self, points: &[Complex<f64>], counts: &mut [u32]) {
+        let lanes = match self.lanes() {
+            Some(lanes) => lanes,
+            None => {
+                for (count, &point) in counts.iter_mut().zip(points) {
+                    *count = self.iterations(point);
+                }
+                return;
+            }
+        };
+        let open: Vec<usize> = (0..points.len())
+            .filter(|&i| !self.takes_shortcut(points[i]))
+            .collect();
+        if open.len() == points.len() {
+            iterate(
+                lanes,
+                points,
+                self.julia,
+                self.max_iter,
+                self.bailout,
+                counts,
+            );
+            return;
+        }
+        let points: Vec<Complex<f64>> = open.iter().map(|&i| points[i]).collect();
+        let mut iterated = vec![0; open.len()];
+        iterate(
+            lanes,
+            &points,
+            self.julia,
+            self.max_iter,
+            self.bailout,
+            &mut iterated,
+        );
+        for count in counts.iter_mut() {
+            *count = self.max_iter;
+        }
+        for (&i, &count) in open.iter().zip(&iterated) {
+            counts[i] = count;
+        }
+    }
+
+    ///Returns the iteration count stored for `point`, which is `max_iter` for members of the set.
+    pub fn iterations(&self, point: Complex<f64>) -> u32 {
+        self.escape_iterations(point).unwrap_or(self.max_iter)
+    }
+
+    ///Returns the normalized iteration count of `point`, which is `max_iter` for members of the
+    ///set. The fractional part is computed from how far the orbit overshot the bailout radius,
+    ///so the count is continuous across the bands of whole iteration counts.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A complex number to be determined if it is in the set or not.
+    pub fn smooth_iterations(&self, point: Complex<f64>) -> f32 {
+        match self.escape(point) {
+            None => self.max_iter as f32,
+            Some((i, z)) => {
+                let overshoot =
+                    (z.norm().ln() / self.bailout.ln()).ln() / self.formula.degree().ln();
+                (i as f64 + 1.0 - overshoot).max(0.0) as f32
+            }
         }
     }
-    None
 }
 
-/// Given the row and column of a pixel in the output image, return the
-/// corresponding point on the complex plane.
+///The value of a single pixel which can be computed by a Kernel.
+pub trait Sample: Copy + Default + PartialEq + Send + Sync + 'static {
+    ///Computes the value of the pixel at `point`.
+    fn compute(kernel: &Kernel, point: Complex<f64>) -> Self;
 
-/// # Arguments
-///
-/// * `bounds` - A pair giving the width and height of the image in pixels.
-/// * `pixel` - A (column, row) pair indicating a particular pixel in that image.
-/// * `upper_left` - The upper left point on the complex plane designating the area of the image.
-/// * `lower_right` - The lower right point on the complex plane designating the area of the image.
-pub fn pixel_to_point(
-    bounds: (usize, usize),
-    pixel: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
-) -> Complex<f64> {
-    let (width, height) = (
-        lower_right.re - upper_left.re,
-        upper_left.im - lower_right.im,
-    );
-    Complex {
-        re: upper_left.re + pixel.0 as f64 * width / bounds.0 as f64,
-        // Why subtraction here? pixel.1 increases as we go down,
-        // but the imaginary component increases as we go up.
-        im: upper_left.im - pixel.1 as f64 * height / bounds.1 as f64,
+    ///Computes the values of a row of pixels at `points`.
+    fn compute_row(kernel: &Kernel, points: &[Complex<f64>], row: &mut [Self]) {
+        for (value, &point) in row.iter_mut().zip(points) {
+            *value = Self::compute(kernel, point);
+        }
     }
 }
 
-///Render a rectangle of the Mandelbrot set into a buffer of pixels.
+impl Sample for u32 {
+    fn compute(kernel: &Kernel, point: Complex<f64>) -> Self {
+        kernel.iterations(point)
+    }
+
+    fn compute_row(kernel: &Kernel, points: &[Complex<f64>], row: &mut [Self]) {
+        kernel.row_iterations(points, row)
+    }
+}
+
+impl Sample for f32 {
+    fn compute(kernel: &Kernel, point: Complex<f64>) -> Self {
+        kernel.smooth_iterations(point)
+    }
+}
+
+///A buffer holding one value per pixel of an image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Buffer {
+    ///Whole iteration counts.
+    Iterations(Vec<u32>),
+    ///Smooth iteration counts.
+    Smooth(Vec<f32>),
+}
+
+impl Buffer {
+    ///Returns a zeroed buffer for an image of `len` pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The number of pixels.
+    /// * `smooth` - Decides whether the buffer holds smooth or whole iteration counts.
+    pub fn zeroed(len: usize, smooth: bool) -> Self {
+        if smooth {
+            Buffer::Smooth(vec![0.0; len])
+        } else {
+            Buffer::Iterations(vec![0; len])
+        }
+    }
+
+    ///Returns the number of pixels.
+    pub fn len(&self) -> usize {
+        match self {
+            Buffer::Iterations(pixels) => pixels.len(),
+            Buffer::Smooth(pixels) => pixels.len(),
+        }
+    }
+
+    ///Returns true if the buffer holds no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Returns the iteration count of a pixel as float.
+    pub fn value(&self, index: usize) -> f64 {
+        match self {
+            Buffer::Iterations(pixels) => pixels[index] as f64,
+            Buffer::Smooth(pixels) => pixels[index] as f64,
+        }
+    }
+
+    ///Maps the buffer to one grayscale byte per pixel, see to_grayscale.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_iter` - The iteration limit the buffer was rendered with.
+    pub fn to_grayscale(&self, max_iter: u32) -> Vec<u8> {
+        match self {
+            Buffer::Iterations(pixels) => to_grayscale(pixels, max_iter),
+            Buffer::Smooth(pixels) => pixels
+                .iter()
+                .map(|&value| {
+                    if value >= max_iter as f32 {
+                        0
+                    } else {
+                        (255.0 - value * 256.0 / max_iter as f32).max(0.0) as u8
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    ///Maps the buffer to one 16 bit grayscale value per pixel. Like to_grayscale, but with 65536
+    ///levels, so iteration limits above 256 don't lose information.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_iter` - The iteration limit the buffer was rendered with.
+    pub fn to_grayscale16(&self, max_iter: u32) -> Vec<u16> {
+        (0..self.len())
+            .map(|index| {
+                let value = self.value(index);
+                if value >= max_iter as f64 {
+                    0
+                } else {
+                    (65535.0 - value * 65536.0 / max_iter as f64).max(0.0) as u16
+                }
+            })
+            .collect()
+    }
+
+    ///Returns the buffer as little endian bytes, four per pixel.
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        match self {
+            Buffer::Iterations(pixels) => pixels.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            Buffer::Smooth(pixels) => pixels.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    ///Returns the name of the element type as used by numpy.
+    pub fn dtype(&self) -> &'static str {
+        match self {
+            Buffer::Iterations(_) => "uint32",
+            Buffer::Smooth(_) => "float32",
+        }
+    }
 
+    ///Returns the little endian type descriptor of the elements as used by the npy format.
+    pub fn descr(&self) -> &'static str {
+        match self {
+            Buffer::Iterations(_) => "<u4",
+            Buffer::Smooth(_) => "<f4",
+        }
+    }
+}
+
+///Render a rectangle of the Mandelbrot or a Julia set into a buffer of pixels.
+///
 /// # Arguments
 ///
-/// * `pixels` - A buffer which holds one grayscale pixel per byte.
+/// * `pixels` - A buffer which holds the iteration count of one pixel per element, either whole or smooth.
 /// * `bounds` - A pair giving the width and height of the buffer.
-/// * `upper_left` - The upper left point on the complex plane corresponding to upper left corner of the buffer.
-/// * `lower_right` - The lower right point on the complex plane corresponding to lower right corner of the buffer.
-pub fn render(
-    pixels: &mut [u8],
+/// * `viewport` - The rectangle on the complex plane corresponding to the buffer.
+/// * `kernel` - The parameters of the iteration.
+pub fn render<S: Sample>(
+    pixels: &mut [S],
     bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
+    viewport: Viewport,
+    kernel: &Kernel,
 ) -> Result<(), CustomError> {
     if pixels.len() != bounds.0 * bounds.1 {
         return Err(CustomError::UnfittingArray);
     }
 
-    //Check for every pixel wether it is in the mandelbrot set or not.
+    let transform = viewport.transform(bounds);
+    //Check for every pixel wether it is in the mandelbrot set or not. A whole row is computed at
+    //once, so the vectorised kernel can iterate several pixels together.
     for row in 0..bounds.1 {
-        for column in 0..bounds.0 {
-            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
-            pixels[row * bounds.0 + column] = match escape_mandel_iterations(point) {
-                None => 0,
-                Some(count) => 255 - count as u8,
-            };
-        }
+        let start = row * bounds.0;
+        S::compute_row(
+            kernel,
+            &transform.row(row),
+            &mut pixels[start..start + bounds.0],
+        );
     }
     Ok(())
 }
 
-///Render a rectangle of the Mandelbrot set into a buffer of pixels.
+///Render a rectangle of the Mandelbrot or a Julia set into a buffer of pixels.
 ///
 ///This is a modification of the render function that can be used safely between threads
 ///with the standard library.
-
+///
 /// # Arguments
 ///
-/// * `pixels` - An Arc and Mutex including a buffer the size of the image which holds one grayscale pixel per byte.
+/// * `pixels` - An Arc and Mutex including a buffer the size of the image which holds the iteration count of one pixel per element, either whole or smooth.
 /// * `offset` - An offset which specify which "band" of buffer will be mutated.
 /// * `bounds` - A pair giving the width and height of the band.
-/// * `upper_left` - The upper left point on the complex plane corresponding to upper left corner of the band.
-/// * `lower_right` - The lower right point on the complex plane corresponding to lower right corner of the band.
-pub fn render_threads(
-    pixels: Arc<Mutex<Vec<u8>>>,
+/// * `viewport` - The rectangle on the complex plane corresponding to the band.
+/// * `kernel` - The parameters of the iteration.
+pub fn render_threads<S: Sample>(
+    pixels: Arc<Mutex<Vec<S>>>,
     offset: usize,
     bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
+    viewport: Viewport,
+    kernel: &Kernel,
 ) -> Result<(), CustomError> {
     if offset + bounds.0 * bounds.1 > pixels.lock().unwrap().len() {
         return Err(CustomError::UnfittingArray);
     }
-    //Check for every pixel wether it is in the mandelbrot set or not.
+    let transform = viewport.transform(bounds);
+    let mut values = vec![S::default(); bounds.0];
+    //Check for every pixel wether it is in the mandelbrot set or not, a row at a time.
     for row in 0..bounds.1 {
-        for column in 0..bounds.0 {
-            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
-            let iterations = match escape_mandel_iterations(point) {
-                None => 0,
-                Some(count) => 255 - count as u8,
-            };
+        S::compute_row(kernel, &transform.row(row), &mut values);
+        //The pixels are still written with one lock each, which is what this mechanism measures.
+        for (column, &iterations) in values.iter().enumerate() {
             //Assuming no thread will panic
             pixels.lock().unwrap()[offset + (row * bounds.0 + column)] = iterations;
         }
@@ -125,63 +433,536 @@ pub fn render_threads(
     Ok(())
 }
 
-///Render a rectangle of the Mandelbrot set into a buffer of pixels.
+///Render a rectangle of the Mandelbrot or a Julia set into a buffer of pixels.
 ///
 ///This is a modification of the render function that can be used between threads with the standard
 ///library using unsafe code to avoid Mutex.
-
+///
 /// # Arguments
 ///
-/// * `pixels` - A Raw Pointer to a buffer the size of the image which holds one grayscale pixel per byte.
+/// * `pixels` - A Raw Pointer to a buffer the size of the image which holds the iteration count of one pixel per element, either whole or smooth.
 /// * `offset` - An offset which specify which "band" of buffer will be mutated.
 /// * `bounds` - A pair giving the width and height of the band.
-/// * `upper_left` - The upper left point on the complex plane corresponding to upper left corner of the band.
-/// * `lower_right` - The lower right point on the complex plane corresponding to lower right corner of the band.
-pub fn render_threads_unsafe(
-    pixels: Arc<WrappedUnsafeCell<*mut u8>>,
+/// * `viewport` - The rectangle on the complex plane corresponding to the band.
+/// * `kernel` - The parameters of the iteration.
+pub fn render_threads_unsafe<S: Sample>(
+    pixels: Arc<WrappedUnsafeCell<*mut S>>,
     offset: usize,
     bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
+    viewport: Viewport,
+    kernel: &Kernel,
 ) {
     unsafe {
         //The get() function returns a *mut T pointer which needs to be derferenced
-        //to get its content which is the *mut u8 pointer to the array.
+        //to get its content which is the *mut S pointer to the array.
         let pointer = *pixels.0.get();
+        let transform = viewport.transform(bounds);
+        let mut values = vec![S::default(); bounds.0];
 
-        //Check for every pixel wether it is in the mandelbrot set or not.
+        //Check for every pixel wether it is in the mandelbrot set or not, a row at a time.
         for row in 0..bounds.1 {
-            for column in 0..bounds.0 {
-                let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            S::compute_row(kernel, &transform.row(row), &mut values);
 
-                let mandel_time = match escape_mandel_iterations(point) {
-                    None => 0,
-                    Some(count) => 255 - count as u8,
-                };
+            //write unsafely directly through pointer
+            std::ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                pointer.add(offset + row * bounds.0),
+                bounds.0,
+            );
+        }
+    }
+}
+
+/// Map iteration counts to one grayscale byte per pixel.
+///
+/// Points that escaped quickly are bright, members of the set (`max_iter`) are black.
+/// For `max_iter = 256` this is the same mapping as `255 - count`.
+///
+/// # Arguments
+///
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+pub fn to_grayscale(pixels: &[u32], max_iter: u32) -> Vec<u8> {
+    pixels
+        .iter()
+        .map(|&count| {
+            if count >= max_iter {
+                0
+            } else {
+                (255 - (count as u64 * 256 / max_iter as u64)) as u8
+            }
+        })
+        .collect()
+}
+
+/// Write an image to a file. The format is chosen by the extension of the filename, for example
+/// png, bmp, tiff or jpg. Without a colouring the image is written in grayscale. A png holds the
+/// given text as metadata, see write_text.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the image which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `colouring` - Maps the iteration counts to RGB or RGBA colours.
+/// * `text` - Pairs of a keyword and a text.
+pub fn write_image(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+    text: &[(String, String)],
+) -> Result<(), CustomError> {
+    if filename.to_lowercase().ends_with(".png") {
+        let mut writer = png_writer(filename, bounds, colouring, false)?;
+        write_text(&mut writer, text)?;
+        writer.write_image_data(&png_samples(pixels, max_iter, colouring, false))?;
+        writer.finish()?;
+        return Ok(());
+    }
+    match colouring {
+        None => image::save_buffer(
+            filename,
+            &pixels.to_grayscale(max_iter),
+            bounds.0 as u32,
+            bounds.1 as u32,
+            ColorType::L8,
+        )?,
+        Some(colouring) => image::save_buffer(
+            filename,
+            &colouring.apply(pixels, max_iter),
+            bounds.0 as u32,
+            bounds.1 as u32,
+            if colouring.has_alpha() {
+                ColorType::Rgba8
+            } else {
+                ColorType::Rgb8
+            },
+        )?,
+    }
+    Ok(())
+}
+
+/// Write a png file band by band, so only the bands in flight have to be in memory. `render` is
+/// called once with a sink, which takes the bands of the image from top to bottom. The text
+/// returned by render is written after the image data, for example the time it took.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the image which will be created.
+/// * `bounds` - The dimensions of the image.
+/// * `max_iter` - The iteration limit the image is rendered with.
+/// * `colouring` - Maps the iteration counts to RGB or RGBA colours. The histogram mapping needs
+///   the whole image and is rejected.
+/// * `sixteen_bit` - Decides whether grayscale samples have 16 or 8 bit.
+/// * `text` - Pairs of a keyword and a text, which are written before the image data.
+/// * `render` - Computes the image and passes its bands to the sink.
+pub fn stream_png<F>(
+    filename: &str,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+    sixteen_bit: bool,
+    text: &[(String, String)],
+    render: F,
+) -> Result<(), CustomError>
+where
+    F: FnOnce(
+        &mut dyn FnMut(&Buffer) -> Result<(), CustomError>,
+    ) -> Result<Vec<(String, String)>, CustomError>,
+{
+    if let Some(Mapping::Histogram) = colouring.map(|c| c.mapping) {
+        return Err(CustomError::InvalidArgument);
+    }
+    let mut writer = png_writer(filename, bounds, colouring, sixteen_bit)?;
+    write_text(&mut writer, text)?;
+    let mut stream = writer.stream_writer()?;
+    let mut rows = 0;
+    let trailer = render(&mut |band: &Buffer| {
+        rows += band.len() / bounds.0.max(1);
+        if rows > bounds.1 {
+            return Err(CustomError::UnfittingArray);
+        }
+        stream.write_all(&png_samples(band, max_iter, colouring, sixteen_bit))?;
+        Ok(())
+    })?;
+    if rows != bounds.1 {
+        return Err(CustomError::UnfittingArray);
+    }
+    stream.finish()?;
+    write_text(&mut writer, &trailer)?;
+    writer.finish()?;
+    Ok(())
+}
 
-                //write unsafely directly through pointer
-                pointer
-                    .offset((offset + (row * bounds.0 + column)) as isize)
-                    .write(mandel_time);
+/// Creates a png file and writes its header.
+fn png_writer(
+    filename: &str,
+    bounds: (usize, usize),
+    colouring: Option<&Colouring>,
+    sixteen_bit: bool,
+) -> Result<png::Writer<BufWriter<File>>, CustomError> {
+    let output = BufWriter::new(File::create(filename)?);
+    let mut encoder = png::Encoder::new(output, bounds.0 as u32, bounds.1 as u32);
+    match colouring {
+        None if sixteen_bit => {
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Sixteen);
+        }
+        None => encoder.set_color(png::ColorType::Grayscale),
+        Some(colouring) if colouring.has_alpha() => encoder.set_color(png::ColorType::Rgba),
+        Some(_) => encoder.set_color(png::ColorType::Rgb),
+    }
+    Ok(encoder.write_header()?)
+}
+
+/// Returns the png samples of a buffer matching the header written by png_writer.
+fn png_samples(
+    pixels: &Buffer,
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+    sixteen_bit: bool,
+) -> Vec<u8> {
+    match colouring {
+        //PNG stores 16 bit samples most significant byte first.
+        None if sixteen_bit => pixels
+            .to_grayscale16(max_iter)
+            .into_iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect(),
+        None => pixels.to_grayscale(max_iter),
+        Some(colouring) => colouring.apply(pixels, max_iter),
+    }
+}
+
+/// Writes text metadata into a png file. Each pair is written as tEXt chunk, or as iTXt chunk
+/// in UTF-8 if it isn't plain ASCII. A keyword must have 1 to 79 characters.
+fn write_text<W: Write>(
+    writer: &mut png::Writer<W>,
+    text: &[(String, String)],
+) -> Result<(), CustomError> {
+    for (keyword, value) in text {
+        if keyword.is_empty() || keyword.len() > 79 || !keyword.is_ascii() {
+            return Err(CustomError::InvalidArgument);
+        }
+        if value.is_ascii() {
+            writer.write_text_chunk(&TEXtChunk::new(keyword.clone(), value.clone()))?;
+        } else {
+            writer.write_text_chunk(&ITXtChunk::new(keyword.clone(), value.clone()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the tEXt and uncompressed iTXt chunks of a png file. Returns pairs of a keyword and a
+/// text in the order of the file.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the png file.
+pub fn read_text(filename: &str) -> Result<Vec<(String, String)>, CustomError> {
+    let content = std::fs::read(filename)?;
+    if !content.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Err(CustomError::InvalidArgument);
+    }
+    let mut text = vec![];
+    //Every chunk holds its length, its name, the data and a checksum.
+    let mut position = 8;
+    while position + 8 <= content.len() {
+        let length = u32::from_be_bytes([
+            content[position],
+            content[position + 1],
+            content[position + 2],
+            content[position + 3],
+        ]) as usize;
+        let name = &content[position + 4..position + 8];
+        let start = position + 8;
+        if start + length > content.len() {
+            return Err(CustomError::InvalidArgument);
+        }
+        let data = &content[start..start + length];
+        let split = data.iter().position(|b| *b == 0);
+        match (name, split) {
+            (b"tEXt", Some(i)) => {
+                //tEXt is encoded in Latin-1, which maps directly to the first code points.
+                let latin1 = |bytes: &[u8]| bytes.iter().map(|b| *b as char).collect();
+                text.push((latin1(&data[..i]), latin1(&data[i + 1..])));
+            }
+            (b"iTXt", Some(i)) if data.get(i + 1) == Some(&0) => {
+                //Skip the compression method, the language tag and the translated keyword.
+                let rest = &data[(i + 3).min(data.len())..];
+                let rest = match rest.iter().position(|b| *b == 0) {
+                    Some(j) => &rest[j + 1..],
+                    None => return Err(CustomError::InvalidArgument),
+                };
+                let value = match rest.iter().position(|b| *b == 0) {
+                    Some(j) => &rest[j + 1..],
+                    None => return Err(CustomError::InvalidArgument),
+                };
+                text.push((
+                    String::from_utf8_lossy(&data[..i]).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                ));
             }
+            (b"IEND", _) => break,
+            _ => {}
         }
+        position = start + length + 4;
     }
+    Ok(text)
 }
 
-/// Write an image to a png file
+/// Write a binary PGM (P5) grayscale image without any dependencies.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the image which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `sixteen_bit` - Decides whether the samples have 16 or 8 bit.
+pub fn write_pgm(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+    max_iter: u32,
+    sixteen_bit: bool,
+) -> Result<(), CustomError> {
+    let mut output = BufWriter::new(File::create(filename)?);
+    if sixteen_bit {
+        output.write_fmt(format_args!("P5\n{} {}\n65535\n", bounds.0, bounds.1))?;
+        //PGM stores 16 bit samples most significant byte first.
+        for value in pixels.to_grayscale16(max_iter) {
+            output.write_all(&value.to_be_bytes())?;
+        }
+    } else {
+        output.write_fmt(format_args!("P5\n{} {}\n255\n", bounds.0, bounds.1))?;
+        output.write_all(&pixels.to_grayscale(max_iter))?;
+    }
+    output.flush()?;
+    Ok(())
+}
 
+/// Write a binary PPM (P6) colour image without any dependencies. Without a colouring the
+/// grayscale values are written to all three channels, an alpha channel is dropped.
+///
 /// # Arguments
 ///
 /// * `filename` - The name of the image which will be created.
-/// * `pixels` - A buffer holding one pixel per byte in grayscale.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
 /// * `bounds` - The dimensions of the image.
-pub fn write_image(
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `colouring` - Maps the iteration counts to colours.
+pub fn write_ppm(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+) -> Result<(), CustomError> {
+    let mut output = BufWriter::new(File::create(filename)?);
+    output.write_fmt(format_args!("P6\n{} {}\n255\n", bounds.0, bounds.1))?;
+    output.write_all(&rgb(pixels, max_iter, colouring))?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Write the iteration counts without a header as little endian uint32, or float32 for smooth
+/// counts, row by row. A JSON sidecar named `filename.json` describes the dimensions, the data
+/// type and the viewport.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the dump which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+/// * `viewport` - The rectangle on the complex plane designating the area of the image.
+/// * `kernel` - The parameters of the iteration the buffer was rendered with.
+pub fn write_raw(
     filename: &str,
-    pixels: &[u8],
+    pixels: &Buffer,
     bounds: (usize, usize),
+    viewport: Viewport,
+    kernel: &Kernel,
 ) -> Result<(), CustomError> {
-    let output = File::create(filename)?;
-    let encoder = PNGEncoder::new(output);
-    encoder.encode(&pixels, bounds.0 as u32, bounds.1 as u32, ColorType::L8)?;
+    let mut output = BufWriter::new(File::create(filename)?);
+    output.write_all(&pixels.to_le_bytes())?;
+    output.flush()?;
+
+    let julia = match kernel.julia {
+        Some(c) => format!("[{:?}, {:?}]", c.re, c.im),
+        None => "null".to_string(),
+    };
+    let (upper_left, lower_right) = (viewport.upper_left, viewport.lower_right);
+    let mut sidecar = File::create(format!("{}.json", filename))?;
+    writeln!(sidecar, "{{")?;
+    writeln!(sidecar, "  \"width\": {},", bounds.0)?;
+    writeln!(sidecar, "  \"height\": {},", bounds.1)?;
+    writeln!(sidecar, "  \"dtype\": \"{}\",", pixels.dtype())?;
+    writeln!(sidecar, "  \"byte_order\": \"little\",")?;
+    writeln!(sidecar, "  \"max_iter\": {},", kernel.max_iter)?;
+    writeln!(
+        sidecar,
+        "  \"upper_left\": [{:?}, {:?}],",
+        upper_left.re, upper_left.im
+    )?;
+    writeln!(
+        sidecar,
+        "  \"lower_right\": [{:?}, {:?}],",
+        lower_right.re, lower_right.im
+    )?;
+    writeln!(sidecar, "  \"formula\": \"{}\",", kernel.formula.name())?;
+    writeln!(sidecar, "  \"julia\": {},", julia)?;
+    writeln!(sidecar, "  \"bailout\": {:?}", kernel.bailout)?;
+    writeln!(sidecar, "}}")?;
+    Ok(())
+}
+
+/// Write the iteration counts as NumPy array in the NPY format version 1.0. The array has the
+/// shape (height, width) and the dtype uint32, or float32 for smooth counts.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the file which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+pub fn write_npy(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+) -> Result<(), CustomError> {
+    if pixels.len() != bounds.0 * bounds.1 {
+        return Err(CustomError::UnfittingArray);
+    }
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        pixels.descr(),
+        bounds.1,
+        bounds.0
+    );
+    //The magic string, the version and the header length take 10 bytes. The header is padded
+    //with spaces and ends with a newline, so the data starts at a multiple of 64 bytes.
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    if header.len() > u16::MAX as usize {
+        return Err(CustomError::UnfittingArray);
+    }
+
+    let mut output = BufWriter::new(File::create(filename)?);
+    output.write_all(b"\x93NUMPY\x01\x00")?;
+    output.write_all(&(header.len() as u16).to_le_bytes())?;
+    output.write_all(header.as_bytes())?;
+    output.write_all(&pixels.to_le_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Write a 16 bit grayscale png file, see Buffer::to_grayscale16.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the image which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `text` - Pairs of a keyword and a text, see write_text.
+pub fn write_png16(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+    max_iter: u32,
+    text: &[(String, String)],
+) -> Result<(), CustomError> {
+    let mut writer = png_writer(filename, bounds, None, true)?;
+    write_text(&mut writer, text)?;
+    writer.write_image_data(&png_samples(pixels, max_iter, None, true))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write an uncompressed OpenEXR image with a single 32 bit float channel Y holding the whole or
+/// smooth iteration counts, so they can be coloured again later without loss. Members of the set
+/// hold the iteration limit.
+///
+/// # Arguments
+///
+/// * `filename` - The name of the image which will be created.
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+pub fn write_exr(
+    filename: &str,
+    pixels: &Buffer,
+    bounds: (usize, usize),
+) -> Result<(), CustomError> {
+    if pixels.len() != bounds.0 * bounds.1 {
+        return Err(CustomError::UnfittingArray);
+    }
+    let (width, height) = (bounds.0 as i32, bounds.1 as i32);
+
+    //An attribute is stored as name, type, size of the value and value.
+    fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    }
+
+    //Magic number and version 2 for a single part scanline image.
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    //One channel Y of pixel type FLOAT (2), not linear, sampled in every pixel.
+    let mut channels = b"Y\0".to_vec();
+    for value in &[2i32, 0, 1, 1] {
+        channels.extend_from_slice(&value.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+    attribute(&mut header, "compression", "compression", &[0]);
+    let mut window = vec![];
+    for value in &[0, 0, width - 1, height - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let mut output = BufWriter::new(File::create(filename)?);
+    output.write_all(&header)?;
+
+    //Without compression every block holds one scanline: its y coordinate, its size and the
+    //samples. The offset table holds the position of every block in the file.
+    let line_size = bounds.0 * 4;
+    let first_block = header.len() + bounds.1 * 8;
+    for row in 0..bounds.1 {
+        let offset = (first_block + row * (8 + line_size)) as u64;
+        output.write_all(&offset.to_le_bytes())?;
+    }
+    for row in 0..bounds.1 {
+        output.write_all(&(row as i32).to_le_bytes())?;
+        output.write_all(&(line_size as i32).to_le_bytes())?;
+        for column in 0..bounds.0 {
+            let value = pixels.value(row * bounds.0 + column) as f32;
+            output.write_all(&value.to_le_bytes())?;
+        }
+    }
+    output.flush()?;
     Ok(())
 }
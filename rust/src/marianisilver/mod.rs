@@ -0,0 +1,230 @@
+use crate::customerror::CustomError;
+use crate::mandel::{Kernel, Sample};
+use crate::viewport::{Transform, Viewport};
+use std::marker::PhantomData;
+
+///Rectangles with at most this many pixels inside their border are computed pixel by pixel
+///instead of being split further.
+pub const MIN_INTERIOR: usize = 64;
+
+///A rectangle of the image whose border is computed, see MarianiSilver. It can only be obtained
+///from MarianiSilver::new and MarianiSilver::split and is consumed by them, so the interiors of
+///all rectangles of an image are disjoint.
+#[derive(Debug)]
+pub struct Rect {
+    column: usize,
+    row: usize,
+    width: usize,
+    height: usize,
+    ///The address of the pixels of the MarianiSilver the rectangle belongs to.
+    owner: usize,
+}
+
+impl Rect {
+    ///Returns the number of pixels inside the border.
+    pub fn interior(&self) -> usize {
+        self.width.saturating_sub(2) * self.height.saturating_sub(2)
+    }
+}
+
+///Computes an image with the rectangle subdivision of Mariani and Silver. Only the border of a
+///rectangle is iterated. If all pixels of the border have the same value, the interior is filled
+///with it, otherwise the rectangle is split into two halves by a line of computed pixels.
+///
+///This relies on the set being connected, so a region which is surrounded by one iteration count
+///has that count everywhere. It is not true for every formula and viewport, a thin filament can
+///pass between the pixels of a border. See Validation in the mechanism module, which compares the
+///result with the one of brute force.
+///
+///The halves of a split rectangle only read the pixels of their borders, which were computed
+///before, and only write their interiors, so they can be computed by different threads.
+pub struct MarianiSilver<'a, S> {
+    pixels: *mut S,
+    bounds: (usize, usize),
+    transform: Transform,
+    kernel: &'a Kernel,
+    _pixels: PhantomData<&'a mut [S]>,
+}
+
+unsafe impl<'a, S: Send> Send for MarianiSilver<'a, S> {}
+unsafe impl<'a, S: Send> Sync for MarianiSilver<'a, S> {}
+
+impl<'a, S: Sample> MarianiSilver<'a, S> {
+    ///Computes the border of the image. Returns the subdivision and the rectangle of the whole
+    ///image, which is passed to split or finish.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A buffer which holds the iteration count of one pixel per element, either whole or smooth.
+    /// * `bounds` - A pair giving the width and height of the buffer.
+    /// * `viewport` - The rectangle on the complex plane corresponding to the buffer.
+    /// * `kernel` - The parameters of the iteration.
+    pub fn new(
+        pixels: &'a mut [S],
+        bounds: (usize, usize),
+        viewport: Viewport,
+        kernel: &'a Kernel,
+    ) -> Result<(Self, Rect), CustomError> {
+        if pixels.len() != bounds.0 * bounds.1 {
+            return Err(CustomError::UnfittingArray);
+        }
+        let subdivision = MarianiSilver {
+            pixels: pixels.as_mut_ptr(),
+            bounds,
+            transform: viewport.transform(bounds),
+            kernel,
+            _pixels: PhantomData,
+        };
+        if bounds.0 > 0 && bounds.1 > 0 {
+            subdivision.row(0, 0, bounds.0);
+            subdivision.row(bounds.1 - 1, 0, bounds.0);
+            subdivision.column(0, 1, bounds.1 - 1);
+            subdivision.column(bounds.0 - 1, 1, bounds.1 - 1);
+        }
+        let rect = Rect {
+            column: 0,
+            row: 0,
+            width: bounds.0,
+            height: bounds.1,
+            owner: subdivision.pixels as usize,
+        };
+        Ok((subdivision, rect))
+    }
+
+    ///Computes the interior of a rectangle if its border has one value or it is small. Otherwise
+    ///computes the line through its middle across the longer side and returns the two halves.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - A rectangle of this subdivision.
+    pub fn split(&self, rect: Rect) -> Option<(Rect, Rect)> {
+        assert_eq!(rect.owner, self.pixels as usize);
+        if rect.interior() == 0 {
+            return None;
+        }
+        let (column, row, width, height) = (rect.column, rect.row, rect.width, rect.height);
+        if let Some(value) = self.uniform(&rect) {
+            for y in row + 1..row + height - 1 {
+                for x in column + 1..column + width - 1 {
+                    self.set(x, y, value);
+                }
+            }
+            return None;
+        }
+        if rect.interior() <= MIN_INTERIOR {
+            for y in row + 1..row + height - 1 {
+                self.row(y, column + 1, column + width - 1);
+            }
+            return None;
+        }
+        //The line belongs to the borders of both halves.
+        let halves = if width >= height {
+            let middle = column + width / 2;
+            self.column(middle, row + 1, row + height - 1);
+            (
+                Rect {
+                    width: middle - column + 1,
+                    ..rect
+                },
+                Rect {
+                    column: middle,
+                    width: column + width - middle,
+                    ..rect
+                },
+            )
+        } else {
+            let middle = row + height / 2;
+            self.row(middle, column + 1, column + width - 1);
+            (
+                Rect {
+                    height: middle - row + 1,
+                    ..rect
+                },
+                Rect {
+                    row: middle,
+                    height: row + height - middle,
+                    ..rect
+                },
+            )
+        };
+        Some(halves)
+    }
+
+    ///Computes a rectangle completely on the calling thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - A rectangle of this subdivision.
+    pub fn finish(&self, rect: Rect) {
+        if let Some((first, second)) = self.split(rect) {
+            self.finish(first);
+            self.finish(second);
+        }
+    }
+
+    ///Returns the value of the border of a rectangle if all its pixels have the same one.
+    fn uniform(&self, rect: &Rect) -> Option<S> {
+        let (right, bottom) = (rect.column + rect.width - 1, rect.row + rect.height - 1);
+        let value = self.get(rect.column, rect.row);
+        let horizontal = (rect.column..=right)
+            .all(|x| self.get(x, rect.row) == value && self.get(x, bottom) == value);
+        let vertical = (rect.row..=bottom)
+            .all(|y| self.get(rect.column, y) == value && self.get(right, y) == value);
+        if horizontal && vertical {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    ///Computes the pixels of a row from column `start` up to `end`, which is excluded. The row is
+    ///computed at once, so the vectorised kernel can iterate several pixels together.
+    fn row(&self, row: usize, start: usize, end: usize) {
+        let points: Vec<_> = (start..end)
+            .map(|x| self.transform.pixel_to_point((x, row)))
+            .collect();
+        let mut values = vec![S::default(); points.len()];
+        S::compute_row(self.kernel, &points, &mut values);
+        for (x, value) in (start..end).zip(values) {
+            self.set(x, row, value);
+        }
+    }
+
+    ///Computes the pixels of a column from row `start` up to `end`, which is excluded.
+    fn column(&self, column: usize, start: usize, end: usize) {
+        for y in start..end {
+            let point = self.transform.pixel_to_point((column, y));
+            self.set(column, y, S::compute(self.kernel, point));
+        }
+    }
+
+    fn get(&self, column: usize, row: usize) -> S {
+        assert!(column < self.bounds.0 && row < self.bounds.1);
+        unsafe { self.pixels.add(row * self.bounds.0 + column).read() }
+    }
+
+    fn set(&self, column: usize, row: usize, value: S) {
+        assert!(column < self.bounds.0 && row < self.bounds.1);
+        unsafe { self.pixels.add(row * self.bounds.0 + column).write(value) }
+    }
+}
+
+///Render a rectangle of the Mandelbrot or a Julia set into a buffer of pixels with the rectangle
+///subdivision of Mariani and Silver on the calling thread, see MarianiSilver.
+///
+/// # Arguments
+///
+/// * `pixels` - A buffer which holds the iteration count of one pixel per element, either whole or smooth.
+/// * `bounds` - A pair giving the width and height of the buffer.
+/// * `viewport` - The rectangle on the complex plane corresponding to the buffer.
+/// * `kernel` - The parameters of the iteration.
+pub fn render_mariani_silver<S: Sample>(
+    pixels: &mut [S],
+    bounds: (usize, usize),
+    viewport: Viewport,
+    kernel: &Kernel,
+) -> Result<(), CustomError> {
+    let (subdivision, rect) = MarianiSilver::new(pixels, bounds, viewport, kernel)?;
+    subdivision.finish(rect);
+    Ok(())
+}
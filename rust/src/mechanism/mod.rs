@@ -0,0 +1,553 @@
+use crate::customerror::CustomError;
+use crate::formula::Orbit;
+use crate::mandel::{render, Buffer, Kernel, Sample};
+use crate::marianisilver::render_mariani_silver;
+use crate::output::ImageOutput;
+use crate::time::{Clock, MyTimestamp};
+use crate::viewport::Viewport;
+use crate::wcrossbeam::Crossbeam;
+use crate::wrayon::Rayon;
+use crate::wscopedthreadpool::ScopedThreadpool;
+use crate::wthreads::Threads;
+use crate::wthreadsunsafe::ThreadsUnsafe;
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+///Holds everything a mechanism needs to know to compute an image of the mandelbrot set.
+#[derive(Clone, Debug)]
+pub struct RenderJob {
+    ///A pair giving the width and height of the image in pixels.
+    pub bounds: (usize, usize),
+    ///The rectangle on the complex plane designating the area of the image.
+    pub viewport: Viewport,
+    ///The number of threads, which is also the number of bands for the thread based mechanisms.
+    pub threads: usize,
+    ///Specifies how big a band will be for the mechanisms which don't split by threads.
+    pub rows_per_band: usize,
+    ///The parameters of the iteration which decides the value of a single pixel.
+    pub kernel: Kernel,
+    ///Decides which pixels are iterated.
+    pub strategy: Strategy,
+}
+
+///Decides which pixels of an image are iterated, see RenderJob::strategy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    ///Every pixel is iterated.
+    BruteForce,
+    ///Only the borders of rectangles are iterated and uniform ones are filled, see
+    ///MarianiSilver. The rayon and the scoped_threadpool mechanism compute the rectangles in
+    ///parallel, the other mechanisms iterate every pixel.
+    MarianiSilver,
+}
+
+impl Strategy {
+    ///Parses a strategy from its name, see name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the strategy.
+    pub fn parse(name: &str) -> Result<Self, CustomError> {
+        match name {
+            "brute-force" => Ok(Strategy::BruteForce),
+            "mariani-silver" => Ok(Strategy::MarianiSilver),
+            _ => Err(CustomError::InvalidArgument),
+        }
+    }
+
+    ///Returns the name which selects the strategy on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::BruteForce => "brute-force",
+            Strategy::MarianiSilver => "mariani-silver",
+        }
+    }
+
+    ///Render a rectangle of the Mandelbrot or a Julia set into a buffer of pixels with the
+    ///strategy on the calling thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A buffer which holds the iteration count of one pixel per element, either whole or smooth.
+    /// * `bounds` - A pair giving the width and height of the buffer.
+    /// * `viewport` - The rectangle on the complex plane corresponding to the buffer.
+    /// * `kernel` - The parameters of the iteration.
+    pub fn render<S: Sample>(
+        &self,
+        pixels: &mut [S],
+        bounds: (usize, usize),
+        viewport: Viewport,
+        kernel: &Kernel,
+    ) -> Result<(), CustomError> {
+        match self {
+            Strategy::BruteForce => render(pixels, bounds, viewport, kernel),
+            Strategy::MarianiSilver => render_mariani_silver(pixels, bounds, viewport, kernel),
+        }
+    }
+}
+
+impl RenderJob {
+    ///Returns the rows per band if the image is split into one band per thread.
+    //is_multiple_of needs Rust 1.87.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn rows_per_thread(&self) -> usize {
+        //if threads doesn't fit perfectly in height without rest, it must be round upward to make sure that the bands cover the entire image.
+        if self.bounds.1 % self.threads == 0 {
+            self.bounds.1 / self.threads
+        } else {
+            self.bounds.1 / self.threads + 1
+        }
+    }
+
+    ///Returns the job for the band of `height` rows starting at row `top`.
+    ///
+    /// # Arguments
+    ///
+    /// * `top` - The first row of the band.
+    /// * `height` - The number of rows of the band.
+    pub fn band(&self, top: usize, height: usize) -> RenderJob {
+        self.region((0, top), (self.bounds.0, height))
+    }
+
+    ///Returns the job for a rectangle of the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `corner` - The column and row of the upper left pixel of the rectangle.
+    /// * `size` - The width and height of the rectangle in pixels.
+    pub fn region(&self, corner: (usize, usize), size: (usize, usize)) -> RenderJob {
+        RenderJob {
+            bounds: size,
+            viewport: self.viewport.region(self.bounds, corner, size),
+            ..self.clone()
+        }
+    }
+
+    ///Splits the image into non overlapping bands. The last band can be smaller than the others.
+    ///Returns the first row and the job of each band.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows_per_band` - Specifies how big a band will be.
+    pub fn bands(&self, rows_per_band: usize) -> Vec<(usize, RenderJob)> {
+        (0..self.bounds.1)
+            .step_by(rows_per_band)
+            .map(|top| {
+                let height = rows_per_band.min(self.bounds.1 - top);
+                (top, self.band(top, height))
+            })
+            .collect()
+    }
+
+    ///Returns the fraction of the pixels which take the shortcut of the kernel, see
+    ///Kernel::takes_shortcut. Only the membership test is repeated, there is no iteration.
+    pub fn shortcut_fraction(&self) -> f64 {
+        let transform = self.viewport.transform(self.bounds);
+        let mut count = 0;
+        for row in 0..self.bounds.1 {
+            for column in 0..self.bounds.0 {
+                if self
+                    .kernel
+                    .takes_shortcut(transform.pixel_to_point((column, row)))
+                {
+                    count += 1;
+                }
+            }
+        }
+        count as f64 / (self.bounds.0 * self.bounds.1).max(1) as f64
+    }
+
+    ///Returns the job with the plain kernel, which neither takes the shortcut nor detects
+    ///periodic orbits.
+    pub fn plain(&self) -> RenderJob {
+        let mut job = self.clone();
+        job.kernel.shortcut = false;
+        job.kernel.periodicity = false;
+        job
+    }
+
+    ///Returns the job with the brute force strategy, which iterates every pixel.
+    pub fn brute_force(&self) -> RenderJob {
+        RenderJob {
+            strategy: Strategy::BruteForce,
+            ..self.clone()
+        }
+    }
+
+    ///Counts the iterations of every pixel of the image with the kernel of the job, see
+    ///Kernel::orbit. They are counted on the calling thread without timing.
+    pub fn iteration_cost(&self) -> IterationCost {
+        let transform = self.viewport.transform(self.bounds);
+        let mut cost = IterationCost::default();
+        for row in 0..self.bounds.1 {
+            for column in 0..self.bounds.0 {
+                match self.kernel.orbit(transform.pixel_to_point((column, row))) {
+                    Orbit::Member(i) => {
+                        cost.members += 1;
+                        cost.interior += i as u64;
+                    }
+                    //An escaping orbit costs the iteration it escaped in, too.
+                    Orbit::Escaped(i, _) => cost.exterior += i as u64 + 1,
+                }
+            }
+        }
+        cost
+    }
+
+    ///Computes the pixels of the image on the calling thread with the strategy of the job.
+    pub fn render(&self) -> Result<Buffer, CustomError> {
+        let len = self.bounds.0 * self.bounds.1;
+        if self.kernel.smooth {
+            let mut pixels = vec![0.0; len];
+            self.strategy
+                .render(&mut pixels, self.bounds, self.viewport, &self.kernel)?;
+            Ok(Buffer::Smooth(pixels))
+        } else {
+            let mut pixels = vec![0; len];
+            self.strategy
+                .render(&mut pixels, self.bounds, self.viewport, &self.kernel)?;
+            Ok(Buffer::Iterations(pixels))
+        }
+    }
+}
+
+///The iterations the pixels of an image cost, split into the members of the set and the points
+///which escape.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IterationCost {
+    ///The number of pixels which are members of the set.
+    pub members: usize,
+    ///The iterations of the members.
+    pub interior: u64,
+    ///The iterations of the points which escape.
+    pub exterior: u64,
+}
+
+impl IterationCost {
+    ///Returns the iterations of all pixels.
+    pub fn total(&self) -> u64 {
+        self.interior + self.exterior
+    }
+}
+
+///Compares the iterations of an image with the plain kernel to the iterations with the shortcut
+///and the periodicity detection of its kernel.
+pub struct CostReport {
+    ///The iterations with the plain kernel.
+    pub plain: IterationCost,
+    ///The iterations with the kernel of the job.
+    pub cost: IterationCost,
+}
+
+impl CostReport {
+    ///Counts the iterations of job with both kernels, see RenderJob::iteration_cost.
+    pub fn new(job: &RenderJob) -> Self {
+        CostReport {
+            plain: job.plain().iteration_cost(),
+            cost: job.iteration_cost(),
+        }
+    }
+
+    ///Returns the share of the saved iterations which were saved on members of the set. It is 0
+    ///if no iterations were saved.
+    pub fn interior_share(&self) -> f64 {
+        let saved = self.plain.total() as f64 - self.cost.total() as f64;
+        if saved <= 0.0 {
+            return 0.0;
+        }
+        (self.plain.interior as f64 - self.cost.interior as f64) / saved
+    }
+
+    ///Runs the job with the plain kernel and with its own kernel on a mechanism. Returns both
+    ///times in ms.
+    ///
+    /// # Arguments
+    ///
+    /// * `mechanism` - The mechanism to measure.
+    /// * `job` - The image to compute.
+    pub fn measure(mechanism: &dyn Mechanism, job: &RenderJob) -> Result<(f64, f64), CustomError> {
+        let plain = mechanism.run(&job.plain())?.time;
+        let time = mechanism.run(job)?.time;
+        Ok((plain, time))
+    }
+}
+
+///Compares the pixels a mechanism computes with the strategy of a job to the pixels it computes
+///with brute force.
+pub struct Validation {
+    ///The number of pixels of the image.
+    pub pixels: usize,
+    ///The number of pixels which differ from brute force.
+    pub differing: usize,
+    ///The strategy the mechanism computed the job with, see Mechanism::strategy. If it is brute
+    ///force, brute force was compared with itself.
+    pub strategy: Strategy,
+    ///The time in ms with the strategy.
+    pub time: f64,
+    ///The time in ms with brute force.
+    pub brute_force: f64,
+}
+
+impl Validation {
+    ///Runs the job with its strategy and with brute force on a mechanism and compares the pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `mechanism` - The mechanism to validate.
+    /// * `job` - The image to compute.
+    pub fn run(mechanism: &dyn Mechanism, job: &RenderJob) -> Result<Self, CustomError> {
+        let result = mechanism.run(job)?;
+        let expected = mechanism.run(&job.brute_force())?;
+        let pixels = result.pixels.len();
+        let differing = (0..pixels)
+            .filter(|&i| result.pixels.value(i) != expected.pixels.value(i))
+            .count();
+        Ok(Validation {
+            pixels,
+            differing,
+            strategy: mechanism.strategy(job),
+            time: result.time,
+            brute_force: expected.time,
+        })
+    }
+
+    ///Returns true if every pixel is the same as with brute force.
+    pub fn passed(&self) -> bool {
+        self.differing == 0
+    }
+}
+
+///Receives the computed pixels of a unit together with its index, see Mechanism::compute_units.
+pub type UnitSink<'a> = dyn Fn(usize, Buffer) -> Result<(), CustomError> + Sync + 'a;
+
+///The result of a mechanism.
+pub struct RenderResult {
+    ///A buffer holding the iteration count of one pixel per element.
+    pub pixels: Buffer,
+    ///The time in ms it took to compute the pixels.
+    pub time: f64,
+}
+
+///Decides which parameter of a RenderJob is varied by the workload measure.
+pub enum Partitioning {
+    ///The image is split into one band per thread.
+    Threads,
+    ///The image is split into bands of rows_per_band rows.
+    RowsPerBand,
+}
+
+///A way of computing an image of the mandelbrot set in parallel.
+///
+///Implement this trait and register it to a Registry to make a new mechanism available.
+pub trait Mechanism: Send + Sync {
+    ///The name used to select the mechanism on the command line.
+    fn name(&self) -> &'static str;
+
+    ///A short alias of the name.
+    fn alias(&self) -> &'static str;
+
+    ///A human readable description used in the output.
+    fn label(&self) -> &'static str;
+
+    ///The parameter which specifies how the image is split.
+    fn partitioning(&self) -> Partitioning;
+
+    ///The strategy the mechanism computes job with. The default ignores the strategy of job and
+    ///computes every pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The image to compute.
+    fn strategy(&self, _job: &RenderJob) -> Strategy {
+        Strategy::BruteForce
+    }
+
+    ///Computes the pixels of the image described by job.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The image to compute.
+    /// * `pixels` - A zeroed buffer the size of the image. It is returned after the computation.
+    fn compute(&self, job: &RenderJob, pixels: Buffer) -> Result<Buffer, CustomError>;
+
+    ///The file the workload measure writes its results to.
+    fn measure_file(&self) -> String {
+        format!("rust_{}_performance.txt", self.name())
+    }
+
+    ///Measure in ms how long it takes to compute an image of the mandelbrot set with this mechanism.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The image to compute.
+    fn run(&self, job: &RenderJob) -> Result<RenderResult, CustomError> {
+        let pixels = Buffer::zeroed(job.bounds.0 * job.bounds.1, job.kernel.smooth);
+
+        let mut start = MyTimestamp::new();
+        let mut end = MyTimestamp::new();
+
+        start.gettime(Clock::ClockMonotonicRaw)?;
+        let pixels = self.compute(job, pixels)?;
+        end.gettime(Clock::ClockMonotonicRaw)?;
+
+        Ok(RenderResult {
+            pixels,
+            time: start.compute_time_millis(end),
+        })
+    }
+
+    ///Computes many small independent images in parallel, each on a single thread, and passes
+    ///them to sink as soon as they are done. The order of the calls of sink is undefined.
+    ///The default implementation spawns threads with the standard library, which take the next
+    ///unit until all units are done.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - The images to compute.
+    /// * `threads` - The number of threads.
+    /// * `sink` - Takes the index of a unit and its pixels, for example to write them to a file.
+    fn compute_units(
+        &self,
+        units: &[RenderJob],
+        threads: usize,
+        sink: &UnitSink,
+    ) -> Result<(), CustomError> {
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| -> Result<(), CustomError> {
+            let handles: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| -> Result<(), CustomError> {
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            match units.get(index) {
+                                Some(unit) => sink(index, unit.render()?)?,
+                                None => return Ok(()),
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join()??;
+            }
+            Ok(())
+        })
+    }
+
+    ///Computes the image in slabs of threads * rows_per_band rows and passes them to sink from
+    ///top to bottom. Each slab is computed in parallel by compute, so only the bands of one slab
+    ///are in memory at a time. Returns the time in ms it took to compute the pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The image to compute.
+    /// * `sink` - Takes the computed slabs, for example to write them to a file.
+    fn stream(
+        &self,
+        job: &RenderJob,
+        sink: &mut dyn FnMut(&Buffer) -> Result<(), CustomError>,
+    ) -> Result<f64, CustomError> {
+        let mut time = 0.0;
+        for (_, slab) in job.bands(job.threads * job.rows_per_band) {
+            let result = self.run(&slab)?;
+            sink(&result.pixels)?;
+            time += result.time;
+        }
+        Ok(time)
+    }
+}
+
+///Measures how long it takes for every number of threads in a range from 4 to 80, or for every
+///number of rows per band in a range from 1 to 80, depending on the partitioning of the mechanism.
+///Each measurement is repeated 20 times. The results are written to a file.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism to measure.
+/// * `job` - The image to compute. The varied parameter is overwritten.
+/// * `output` - Decides where the computed images are written. If None, no image is written.
+pub fn measure_workload(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    output: Option<&ImageOutput>,
+) -> Result<(), CustomError> {
+    let mut file = File::create(mechanism.measure_file())?;
+
+    let range = match mechanism.partitioning() {
+        Partitioning::Threads => 4..=80,
+        Partitioning::RowsPerBand => 1..=80,
+    };
+
+    for parameter in range {
+        let mut job = job.clone();
+        match mechanism.partitioning() {
+            Partitioning::Threads => job.threads = parameter,
+            Partitioning::RowsPerBand => job.rows_per_band = parameter,
+        }
+        let mut time: f64 = 0.0;
+        for _ in 0..20 {
+            let result = mechanism.run(&job)?;
+            if let Some(output) = output {
+                output.write(mechanism, &job, &result)?;
+            }
+            time += result.time;
+        }
+        time /= 20.0;
+        file.write_fmt(format_args!("{},{}\n", parameter, time))?;
+    }
+    Ok(())
+}
+
+///Holds all mechanisms which can be selected.
+pub struct Registry {
+    mechanisms: Vec<Box<dyn Mechanism>>,
+}
+
+impl Default for Registry {
+    ///Returns a Registry holding the mechanisms of this crate.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(Threads));
+        registry.register(Box::new(ThreadsUnsafe));
+        registry.register(Box::new(Crossbeam));
+        registry.register(Box::new(ScopedThreadpool::new()));
+        registry.register(Box::new(Rayon));
+        registry
+    }
+}
+
+impl Registry {
+    ///Returns an empty Registry.
+    pub fn new() -> Self {
+        Registry { mechanisms: vec![] }
+    }
+
+    ///Adds a mechanism. Mechanisms are run in the order they were registered.
+    pub fn register(&mut self, mechanism: Box<dyn Mechanism>) {
+        self.mechanisms.push(mechanism);
+    }
+
+    ///Returns all registered mechanisms.
+    pub fn mechanisms(&self) -> Vec<&dyn Mechanism> {
+        self.mechanisms.iter().map(|m| m.as_ref()).collect()
+    }
+
+    ///Returns the mechanisms matching a name or alias, or all mechanisms for "all".
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name or alias of a mechanism or "all".
+    pub fn select(&self, name: &str) -> Result<Vec<&dyn Mechanism>, CustomError> {
+        let selected: Vec<&dyn Mechanism> = self
+            .mechanisms()
+            .into_iter()
+            .filter(|m| name.eq("all") || name.eq(m.name()) || name.eq(m.alias()))
+            .collect();
+        if selected.is_empty() {
+            return Err(CustomError::InvalidArgument);
+        }
+        Ok(selected)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::customerror::CustomError;
+use crate::mandel::{
+    stream_png, write_exr, write_image, write_npy, write_pgm, write_png16, write_ppm, write_raw,
+};
+use crate::mechanism::{Mechanism, RenderJob, RenderResult};
+use crate::palette::{Colouring, Mapping};
+use std::path::Path;
+
+///Makes sure that every mechanism writes its own file by appending _{mechanism} to the file
+///name if the template doesn't hold the placeholder already.
+///
+/// # Arguments
+///
+/// * `template` - A path which may hold placeholders.
+pub fn per_mechanism(template: &str) -> String {
+    append_placeholder(template, "mechanism")
+}
+
+///Makes sure that every frame of an animation is written to its own file by appending _{frame}
+///to the file name if the template doesn't hold the placeholder already.
+///
+/// # Arguments
+///
+/// * `template` - A path which may hold placeholders.
+pub fn per_frame(template: &str) -> String {
+    append_placeholder(template, "frame")
+}
+
+///Replaces the placeholder {frame} of a path by the number of a frame with five digits.
+///
+/// # Arguments
+///
+/// * `template` - A path which may hold placeholders.
+/// * `index` - The number of the frame.
+pub fn expand_frame(template: &str, index: usize) -> String {
+    template.replace("{frame}", &format!("{:05}", index))
+}
+
+///Appends _{name} to the file name of template, in front of the extension, if the template
+///doesn't hold {name} already.
+fn append_placeholder(template: &str, name: &str) -> String {
+    let placeholder = format!("{{{}}}", name);
+    if template.contains(&placeholder) {
+        return template.to_string();
+    }
+    let path = Path::new(template);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => path
+            .with_file_name(format!(
+                "{}_{}.{}",
+                stem.to_string_lossy(),
+                placeholder,
+                extension.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .into_owned(),
+        _ => format!("{}_{}", template, placeholder),
+    }
+}
+
+///Replaces the placeholders {mechanism}, {width}, {height}, {threads}, {rows} and {max_iter}
+///of a path by the values of a render.
+///
+/// # Arguments
+///
+/// * `template` - A path which may hold placeholders.
+/// * `mechanism` - The mechanism which computed the image.
+/// * `job` - The job of the image.
+pub fn expand_template(template: &str, mechanism: &dyn Mechanism, job: &RenderJob) -> String {
+    template
+        .replace("{mechanism}", mechanism.name())
+        .replace("{width}", &job.bounds.0.to_string())
+        .replace("{height}", &job.bounds.1.to_string())
+        .replace("{threads}", &job.threads.to_string())
+        .replace("{rows}", &job.rows_per_band.to_string())
+        .replace("{max_iter}", &job.kernel.max_iter.to_string())
+}
+
+///Describes a render as pairs of a keyword and a text, which are written into png files. The
+///keywords are the long names of the command line options and the texts are given in their
+///format, so --from-image can read them back. The palette is stored as gradient file.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computed the image.
+/// * `job` - The job of the image.
+/// * `colouring` - Maps the iteration counts to colours.
+pub fn metadata(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    colouring: Option<&Colouring>,
+) -> Vec<(String, String)> {
+    let kernel = &job.kernel;
+    let mut text = vec![
+        ("Software".to_string(), "Mandelbrot in Rust".to_string()),
+        ("mechanism".to_string(), mechanism.name().to_string()),
+        (
+            "bounds".to_string(),
+            format!("{},{}", job.bounds.0, job.bounds.1),
+        ),
+        ("complexcoords".to_string(), job.viewport.complexcoords()),
+        ("rotation".to_string(), job.viewport.rotation.to_string()),
+        ("threads".to_string(), job.threads.to_string()),
+        ("rows".to_string(), job.rows_per_band.to_string()),
+        ("max-iter".to_string(), kernel.max_iter.to_string()),
+        ("formula".to_string(), kernel.formula.name()),
+        ("bailout".to_string(), kernel.bailout.to_string()),
+        ("smooth".to_string(), kernel.smooth.to_string()),
+        ("shortcut".to_string(), kernel.shortcut.to_string()),
+        ("periodicity".to_string(), kernel.periodicity.to_string()),
+        (
+            "strategy".to_string(),
+            mechanism.strategy(job).name().to_string(),
+        ),
+    ];
+    if let Some(ref reference) = kernel.reference {
+        let (re, im) = reference.center;
+        text.push(("deep".to_string(), format!("{},{}", re, im)));
+    }
+    if let Some(c) = kernel.julia {
+        text.push(("julia".to_string(), format!("{},{}", c.re, c.im)));
+    }
+    if let Some(colouring) = colouring {
+        let i = colouring.interior;
+        text.push(("palette".to_string(), colouring.gradient.to_text()));
+        if let Some(cycle) = colouring.cycle {
+            text.push(("cycle".to_string(), cycle.to_string()));
+        }
+        text.push(("offset".to_string(), colouring.offset.to_string()));
+        text.push((
+            "interior".to_string(),
+            format!("{},{},{},{}", i[0], i[1], i[2], i[3]),
+        ));
+        text.push((
+            "histogram".to_string(),
+            (colouring.mapping == Mapping::Histogram).to_string(),
+        ));
+    }
+    text
+}
+
+///Decides where and how computed images are written.
+#[derive(Clone, Debug)]
+pub struct ImageOutput {
+    ///The path of the image. It may hold the placeholders {mechanism}, {width}, {height},
+    ///{threads}, {rows} and {max_iter}, which are replaced by the values of the render.
+    pub template: String,
+    ///Maps the iteration counts to colours. If None, the image is written in grayscale.
+    pub colouring: Option<Colouring>,
+    ///The bits per grayscale sample, 8 or 16. Only used by pgm and by png without colouring.
+    pub depth: u8,
+}
+
+impl ImageOutput {
+    ///Returns an ImageOutput which writes grayscale images to the path given by template.
+    pub fn new(template: &str) -> Self {
+        ImageOutput {
+            template: template.to_string(),
+            colouring: None,
+            depth: 8,
+        }
+    }
+
+    ///Makes sure that every mechanism writes its own image, see per_mechanism.
+    pub fn per_mechanism(&mut self) {
+        self.template = per_mechanism(&self.template);
+    }
+
+    ///Returns the path of the image computed by mechanism for job, see expand_template.
+    ///
+    /// # Arguments
+    ///
+    /// * `mechanism` - The mechanism which computed the image.
+    /// * `job` - The job of the image.
+    pub fn path(&self, mechanism: &dyn Mechanism, job: &RenderJob) -> String {
+        expand_template(&self.template, mechanism, job)
+    }
+
+    ///Writes the image computed by mechanism for job. The format is chosen by the extension of
+    ///the path: pgm, ppm and exr are written by the own writers, raw dumps the iteration counts
+    ///with a JSON sidecar, npy writes them as NumPy array and every other extension is passed to
+    ///the image crate. Grayscale png is written with 16 bit if depth is 16. A png holds the
+    ///parameters of the render, see metadata, and the time it took in ms.
+    ///
+    /// # Arguments
+    ///
+    /// * `mechanism` - The mechanism which computed the image.
+    /// * `job` - The job of the image.
+    /// * `result` - The computed pixels and the time it took.
+    pub fn write(
+        &self,
+        mechanism: &dyn Mechanism,
+        job: &RenderJob,
+        result: &RenderResult,
+    ) -> Result<(), CustomError> {
+        let path = self.path(mechanism, job);
+        let pixels = &result.pixels;
+        let mut text = metadata(mechanism, job, self.colouring.as_ref());
+        text.push(("time".to_string(), result.time.to_string()));
+        let extension = Path::new(&path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("pgm") => write_pgm(
+                &path,
+                pixels,
+                job.bounds,
+                job.kernel.max_iter,
+                self.depth == 16,
+            ),
+            Some("ppm") => write_ppm(
+                &path,
+                pixels,
+                job.bounds,
+                job.kernel.max_iter,
+                self.colouring.as_ref(),
+            ),
+            Some("raw") => write_raw(&path, pixels, job.bounds, job.viewport, &job.kernel),
+            Some("npy") => write_npy(&path, pixels, job.bounds),
+            Some("exr") => write_exr(&path, pixels, job.bounds),
+            Some("png") if self.depth == 16 && self.colouring.is_none() => {
+                text.push(("depth".to_string(), "16".to_string()));
+                write_png16(&path, pixels, job.bounds, job.kernel.max_iter, &text)
+            }
+            _ => write_image(
+                &path,
+                pixels,
+                job.bounds,
+                job.kernel.max_iter,
+                self.colouring.as_ref(),
+                &text,
+            ),
+        }
+    }
+
+    ///Computes the image with mechanism and writes it band by band into a png file, so the
+    ///whole image is never in memory, see Mechanism::stream. The histogram mapping is not
+    ///supported. Returns the time in ms it took to compute the pixels.
+    ///
+    /// # Arguments
+    ///
+    /// * `mechanism` - The mechanism which computes the image.
+    /// * `job` - The image to compute.
+    pub fn stream(&self, mechanism: &dyn Mechanism, job: &RenderJob) -> Result<f64, CustomError> {
+        let path = self.path(mechanism, job);
+        if !path.to_lowercase().ends_with(".png") {
+            return Err(CustomError::InvalidArgument);
+        }
+        let mut text = metadata(mechanism, job, self.colouring.as_ref());
+        if self.depth == 16 && self.colouring.is_none() {
+            text.push(("depth".to_string(), "16".to_string()));
+        }
+        let mut time = 0.0;
+        stream_png(
+            &path,
+            job.bounds,
+            job.kernel.max_iter,
+            self.colouring.as_ref(),
+            self.depth == 16,
+            &text,
+            |sink| {
+                time = mechanism.stream(job, sink)?;
+                Ok(vec![("time".to_string(), time.to_string())])
+            },
+        )?;
+        Ok(time)
+    }
+}
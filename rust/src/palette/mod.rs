@@ -0,0 +1,299 @@
+use crate::customerror::CustomError;
+use crate::mandel::Buffer;
+use std::fs;
+
+///A colour with red, green, blue and alpha channel.
+pub type Rgba = [u8; 4];
+
+///A gradient given by colours at positions between 0 and 1. Between two stops the colour is
+///interpolated linearly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f64, Rgba)>,
+}
+
+impl Gradient {
+    ///Returns a gradient of the given stops. Fails if there are no stops or a position is not
+    ///between 0 and 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `stops` - Pairs of a position and the colour at this position.
+    pub fn new(mut stops: Vec<(f64, Rgba)>) -> Result<Self, CustomError> {
+        if stops.is_empty() || stops.iter().any(|(p, _)| !(0.0..=1.0).contains(p)) {
+            return Err(CustomError::InvalidArgument);
+        }
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Ok(Gradient { stops })
+    }
+
+    ///Returns one of the built-in gradients.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - One of gray, fire, ocean, ultra.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let stops: Vec<(f64, Rgba)> = match name {
+            "gray" => vec![(0.0, [255, 255, 255, 255]), (1.0, [0, 0, 0, 255])],
+            "fire" => vec![
+                (0.0, [0, 0, 0, 255]),
+                (0.25, [128, 0, 0, 255]),
+                (0.5, [255, 96, 0, 255]),
+                (0.75, [255, 224, 64, 255]),
+                (0.9, [255, 255, 224, 255]),
+                (1.0, [0, 0, 0, 255]),
+            ],
+            "ocean" => vec![
+                (0.0, [0, 16, 48, 255]),
+                (0.3, [0, 96, 160, 255]),
+                (0.6, [96, 208, 224, 255]),
+                (0.8, [240, 255, 255, 255]),
+                (1.0, [0, 16, 48, 255]),
+            ],
+            "ultra" => vec![
+                (0.0, [0, 7, 100, 255]),
+                (0.16, [32, 107, 203, 255]),
+                (0.42, [237, 255, 255, 255]),
+                (0.6425, [255, 170, 0, 255]),
+                (0.8575, [0, 2, 0, 255]),
+                (1.0, [0, 7, 100, 255]),
+            ],
+            _ => return None,
+        };
+        Some(Gradient { stops })
+    }
+
+    ///Reads a gradient from a file. Each line holds one stop as position,red,green,blue or
+    ///position,red,green,blue,alpha. Empty lines and lines starting with '#' are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The path of the gradient file.
+    pub fn from_file(filename: &str) -> Result<Self, CustomError> {
+        Gradient::parse(&fs::read_to_string(filename)?)
+    }
+
+    ///Parses a gradient given in the format of a gradient file, see from_file.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - One stop per line.
+    pub fn parse(content: &str) -> Result<Self, CustomError> {
+        let mut stops = vec![];
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (position, colour) = match line.find(',') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => return Err(CustomError::InvalidArgument),
+            };
+            stops.push((position.trim().parse::<f64>()?, parse_colour(colour)?));
+        }
+        Gradient::new(stops)
+    }
+
+    ///Returns the gradient in the format of a gradient file, see from_file.
+    pub fn to_text(&self) -> String {
+        self.stops
+            .iter()
+            .map(|(p, c)| format!("{},{},{},{},{}\n", p, c[0], c[1], c[2], c[3]))
+            .collect()
+    }
+
+    ///Returns the colour at position `t` between 0 and 1.
+    pub fn sample(&self, t: f64) -> Rgba {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+        //t lies between the first and the last stop, so there is a stop right of it, unless t is
+        //NaN.
+        let right = match self.stops.iter().position(|(p, _)| *p > t) {
+            Some(right) => right,
+            None => return last.1,
+        };
+        let (p0, c0) = self.stops[right - 1];
+        let (p1, c1) = self.stops[right];
+        let f = (t - p0) / (p1 - p0);
+        let mut colour = [0; 4];
+        for channel in 0..4 {
+            colour[channel] =
+                (c0[channel] as f64 + (c1[channel] as f64 - c0[channel] as f64) * f).round() as u8;
+        }
+        colour
+    }
+}
+
+///Parses a colour given as red,green,blue or red,green,blue,alpha.
+///
+/// # Arguments
+///
+/// * `value` - The comma separated channels.
+pub fn parse_colour(value: &str) -> Result<Rgba, CustomError> {
+    let split: Vec<&str> = value.split(',').map(str::trim).collect();
+    if split.len() != 3 && split.len() != 4 {
+        return Err(CustomError::InvalidArgument);
+    }
+    let mut colour = [255; 4];
+    for (channel, v) in split.iter().enumerate() {
+        colour[channel] = v.parse::<u8>()?;
+    }
+    Ok(colour)
+}
+
+///Decides how an iteration count is mapped to a position on the gradient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapping {
+    ///The position grows linearly with the iteration count.
+    Linear,
+    ///The position is the share of escaped pixels with a smaller iteration count, so every part
+    ///of the gradient colours about the same number of pixels.
+    Histogram,
+}
+
+///The cumulative distribution of the iteration counts of the escaped pixels of a buffer.
+pub struct Histogram {
+    ///cdf[i] is the share of escaped pixels with an iteration count below i.
+    cdf: Vec<f64>,
+}
+
+impl Histogram {
+    ///Counts the iteration counts of a buffer. Smooth counts are counted by their whole part.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A buffer holding the iteration count of one pixel per element.
+    /// * `max_iter` - The iteration limit the buffer was rendered with.
+    pub fn new(pixels: &Buffer, max_iter: u32) -> Self {
+        let mut counts = vec![0u64; max_iter as usize];
+        for index in 0..pixels.len() {
+            let value = pixels.value(index);
+            if value < max_iter as f64 {
+                counts[value as usize] += 1;
+            }
+        }
+        let total = counts.iter().sum::<u64>().max(1) as f64;
+        let mut cdf = Vec::with_capacity(counts.len() + 1);
+        let mut sum = 0;
+        cdf.push(0.0);
+        for count in counts {
+            sum += count;
+            cdf.push(sum as f64 / total);
+        }
+        Histogram { cdf }
+    }
+
+    ///Returns the position of a counted iteration count on the gradient, which lies between 0
+    ///and 1. The position of smooth counts is interpolated between the neighbouring whole counts.
+    pub fn position(&self, value: f64) -> f64 {
+        let i = value as usize;
+        self.cdf[i] + (self.cdf[i + 1] - self.cdf[i]) * value.fract()
+    }
+}
+
+///Decides how iteration counts are mapped to colours.
+#[derive(Clone, Debug)]
+pub struct Colouring {
+    ///The gradient the iteration counts are mapped to.
+    pub gradient: Gradient,
+    ///The number of iterations which pass through the whole gradient once. If None, the gradient
+    ///is stretched over the iteration limit.
+    pub cycle: Option<f64>,
+    ///A shift of the gradient as fraction of one cycle.
+    pub offset: f64,
+    ///The colour of the members of the set.
+    pub interior: Rgba,
+    ///Decides how an iteration count is mapped to a position on the gradient.
+    pub mapping: Mapping,
+}
+
+impl Colouring {
+    ///Returns a Colouring which stretches the gradient over the iteration limit.
+    pub fn new(gradient: Gradient) -> Self {
+        Colouring {
+            gradient,
+            cycle: None,
+            offset: 0.0,
+            interior: [0, 0, 0, 255],
+            mapping: Mapping::Linear,
+        }
+    }
+
+    ///Returns true if the colours need an alpha channel.
+    pub fn has_alpha(&self) -> bool {
+        self.interior[3] != 255 || self.gradient.stops.iter().any(|(_, c)| c[3] != 255)
+    }
+
+    ///Returns the colour of a pixel with the linear mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The whole or smooth iteration count of the pixel.
+    /// * `max_iter` - The iteration limit the pixel was rendered with.
+    pub fn colour(&self, value: f64, max_iter: u32) -> Rgba {
+        if value >= max_iter as f64 {
+            return self.interior;
+        }
+        let t = match self.cycle {
+            Some(cycle) => (value / cycle + self.offset).rem_euclid(1.0),
+            None => (value / max_iter as f64 + self.offset).rem_euclid(1.0),
+        };
+        self.gradient.sample(t)
+    }
+
+    ///Maps a buffer to colours. Returns three bytes per pixel or four if has_alpha is true.
+    ///With the histogram mapping the histogram is built over the whole buffer first, so the
+    ///colours don't depend on how the buffer was computed. The cycle is ignored in this case.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` - A buffer holding the iteration count of one pixel per element.
+    /// * `max_iter` - The iteration limit the buffer was rendered with.
+    pub fn apply(&self, pixels: &Buffer, max_iter: u32) -> Vec<u8> {
+        let channels = if self.has_alpha() { 4 } else { 3 };
+        let histogram = match self.mapping {
+            Mapping::Linear => None,
+            Mapping::Histogram => Some(Histogram::new(pixels, max_iter)),
+        };
+        let mut bytes = Vec::with_capacity(pixels.len() * channels);
+        for index in 0..pixels.len() {
+            let value = pixels.value(index);
+            let colour = match histogram {
+                Some(ref histogram) if value < max_iter as f64 => self
+                    .gradient
+                    .sample((histogram.position(value) + self.offset).rem_euclid(1.0)),
+                _ => self.colour(value, max_iter),
+            };
+            bytes.extend_from_slice(&colour[..channels]);
+        }
+        bytes
+    }
+}
+
+///Returns the red, green and blue bytes of every pixel of a buffer. Alpha is dropped.
+///
+/// # Arguments
+///
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `colouring` - Maps the iteration counts to colours. If None, the pixels are grayscale.
+pub fn rgb(pixels: &Buffer, max_iter: u32, colouring: Option<&Colouring>) -> Vec<u8> {
+    match colouring {
+        Some(colouring) if colouring.has_alpha() => colouring
+            .apply(pixels, max_iter)
+            .chunks(4)
+            .flat_map(|c| c[..3].to_vec())
+            .collect(),
+        Some(colouring) => colouring.apply(pixels, max_iter),
+        None => pixels
+            .to_grayscale(max_iter)
+            .into_iter()
+            .flat_map(|g| vec![g, g, g])
+            .collect(),
+    }
+}
@@ -1,12 +1,29 @@
+use crate::animation::Zoom;
 use crate::customerror::CustomError;
+use crate::formula::{parse_formula, Formula, Mandelbrot};
+use crate::mandel::read_text;
+use crate::mechanism::Strategy;
+use crate::palette::{parse_colour, Colouring, Gradient, Mapping};
+use crate::perturbation::{parse_point, DoubleDouble};
+use crate::simd::Lanes;
+use crate::viewport::Viewport;
+use clap::ArgMatches;
 use num::Complex;
+use std::sync::Arc;
 
 ///defaults
 static BOUNDS: (usize, usize) = (5000, 5000);
 static NTHREADS: usize = 8;
 static ROWS_PER_BAND: usize = 1;
+static MAX_ITER: u32 = 256;
+static BAILOUT: f64 = 2.0;
+static OUTPUT: &str = "mandel.png";
+static FRAMES: usize = 60;
+static FPS: f64 = 25.0;
 static UPPER_LEFT: Complex<f64> = Complex { re: -1.6, im: 1.2 };
 static LOWER_RIGHT: Complex<f64> = Complex { re: 0.6, im: -1.2 };
+static JULIA_UPPER_LEFT: Complex<f64> = Complex { re: -1.5, im: 1.5 };
+static JULIA_LOWER_RIGHT: Complex<f64> = Complex { re: 1.5, im: -1.5 };
 
 ///Holds the parsed or default values
 pub struct ParsedArgs {
@@ -15,9 +32,58 @@ pub struct ParsedArgs {
     pub bounds: (usize, usize),
     pub threads: usize,
     pub rows_per_band: usize,
+    pub max_iter: u32,
     pub draw: bool,
-    pub upper_left: Complex<f64>,
-    pub lower_right: Complex<f64>,
+    pub output: String,
+    pub depth: u8,
+    pub dump_npy: Option<String>,
+    pub stream: bool,
+    pub tiles: Option<String>,
+    pub zoom: Option<Zoom>,
+    pub fps: f64,
+    pub preview: bool,
+    pub explore: bool,
+    pub viewport: Viewport,
+    pub deep: Option<(DoubleDouble, DoubleDouble)>,
+    pub julia: Option<Complex<f64>>,
+    pub formula: Arc<dyn Formula>,
+    pub bailout: f64,
+    pub smooth: bool,
+    pub shortcut: bool,
+    pub periodicity: bool,
+    pub simd: Option<Lanes>,
+    pub cost: bool,
+    pub strategy: Strategy,
+    pub validate: bool,
+    pub colouring: Option<Colouring>,
+}
+
+///Returns the value of an argument. If it is missing, the value stored under key in the png given
+///by --from-image is returned.
+///
+/// # Arguments
+///
+/// * `matches` - The parsed command line.
+/// * `text` - The text metadata of the png given by --from-image.
+/// * `name` - The name of the argument.
+/// * `key` - The keyword of the metadata.
+fn value<'a>(
+    matches: &'a ArgMatches,
+    text: &'a [(String, String)],
+    name: &str,
+    key: &str,
+) -> Option<&'a str> {
+    matches.value_of(name).or_else(|| stored(text, key))
+}
+
+///Returns the text stored under key.
+fn stored<'a>(text: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    text.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+///Returns true if a flag is present or it is stored as true in the png given by --from-image.
+fn flag(matches: &ArgMatches, text: &[(String, String)], name: &str, key: &str) -> bool {
+    matches.is_present(name) || stored(text, key) == Some("true")
 }
 
 ///A fancy cli powered by the clap crate. There is a default value for each unspecified option.
@@ -26,35 +92,59 @@ pub fn parse_arguments() -> Result<ParsedArgs, CustomError> {
     let matches = clap_app!(Mandelbrot =>
         (version: "1.0")
         (author: "Marno Janetzky <janetzkymarno@gmail.com>")
-        (about: "Computes an image of the Mandelbrot set. There is a default value for each unspecified option.")
-        (@arg Mechanism: +takes_value +required -m --mechanism "Mechanisms may be: all, threads|th, threadsunsafe|tu, crossbeam|cb, scoped_threadpool|st, rayon|ra")
+        (about: "Computes an image of the Mandelbrot set or a Julia set. There is a default value for each unspecified option.")
+        (@arg Mechanism: +takes_value required_unless[FromImage] -m --mechanism "Mechanisms may be: all, threads|th, threadsunsafe|tu, crossbeam|cb, scoped_threadpool|st, rayon|ra")
         (@arg Measure: -w --workload "Measures the workload and writes the results to a file. Hint: Consider DrawOff while measuring")
         (@arg Bounds: +takes_value -b --bounds "Set the width and heigth of the image in pixel. Example: 5000,5000")
         (@arg Threads: +takes_value -t --threads "Specify the number of threads. Hint: The rayon mechanism doesn't care about threads")
         (@arg Rows_per_band: +takes_value -r --rows "Specify the rows per band. Hint: Only necessary for scoped_threadpool and rayon")
-        (@arg DrawOff: -d --drawoff "Disables writing the image to a png file")
+        (@arg MaxIter: +takes_value -i --("max-iter") "Specify the maximum number of iterations per pixel. Example: 1024")
+        (@arg Julia: +takes_value +allow_hyphen_values -j --julia "Render the Julia set of the parameter c instead of the Mandelbrot set. Example: For c = -0.8 + 0.156 * I, enter: -0.8,0.156")
+        (@arg Formula: +takes_value -f --formula "Specify the fractal formula. Formulas may be: mandelbrot, burningship, tricorn, multibrot:d for z^d + c with an integer or real exponent d greater than 1. Example: multibrot:3")
+        (@arg Bailout: +takes_value --bailout "Specify the radius an orbit has to leave to escape. Must be at least 2. Hint: Smooth counts look better with a large radius like 256")
+        (@arg Smooth: -s --smooth "Computes smooth iteration counts instead of whole numbers")
+        (@arg Shortcut: --shortcut "Skips the iteration for points in the main cardioid and the period-2 bulb of the Mandelbrot set and reports the fraction of pixels which took this shortcut. Hint: Only the mandelbrot formula without --julia takes it")
+        (@arg Periodicity: --periodicity "Stops the iteration of an orbit when it comes back to an earlier value, which members of the set do after a while. Uses the cycle detection of Brent")
+        (@arg Simd: +takes_value --simd "Computes rows of whole iteration counts with a vectorised kernel which iterates several points at once. The lanes may be: auto for the widest the processor supports, scalar for portable code on four points, sse2 for two, avx2 for four, avx512 for eight. Hint: Only the mandelbrot formula without --smooth and --periodicity is vectorised")
+        (@arg Cost: --cost "Reports how many iterations --shortcut and --periodicity save and which share of them is saved on members of the set, and how much faster each mechanism computes the image with them. Hint: No image is written and neither --workload, --stream, --tiles, --zoom, --preview nor --explore is supported")
+        (@arg Strategy: +takes_value --strategy "Specify which pixels are iterated. Strategies may be: brute-force for every pixel, mariani-silver for only the borders of rectangles, which are filled if the whole border has the same iteration count and split otherwise. Hint: Only rayon and scoped_threadpool compute the rectangles in parallel, the other mechanisms use brute force")
+        (@arg Validate: --validate "Computes the image with the strategy and with brute force and reports how many pixels differ. Exits with an error if any does. Hint: No image is written and neither --workload, --stream, --tiles, --zoom, --preview, --explore nor --cost is supported")
+        (@arg Palette: +takes_value -p --palette "Write a coloured image. Either a built-in palette: gray, fire, ocean, ultra or a gradient file with one stop per line. Example line: 0.5,255,170,0")
+        (@arg Cycle: +takes_value --cycle "Specify the number of iterations which pass through the whole palette once. Default: the iteration limit")
+        (@arg Offset: +takes_value --offset "Shift the palette by a fraction of one cycle. Example: 0.25")
+        (@arg Interior: +takes_value --interior "Specify the colour of the members of the set as red,green,blue or red,green,blue,alpha. Example: 0,0,0,0")
+        (@arg Histogram: -e --histogram "Equalizes the colours by the histogram of the iteration counts. Uses the gray palette if no palette is specified")
+        (@arg Output: +takes_value -o --output "Specify the path of the image. The format is chosen by the extension, raw dumps the iteration counts with a JSON sidecar, exr holds them as float. The placeholders {mechanism}, {width}, {height}, {threads}, {rows} and {max_iter} are replaced by the values of the render. If several mechanisms run and {mechanism} is missing, _{mechanism} is appended to the file name. Example: out/{mechanism}_{threads}.png")
+        (@arg Depth: +takes_value --depth "Specify the bits per grayscale sample, 8 or 16. Hint: Only used by the pgm format and by png without palette")
+        (@arg DumpNpy: +takes_value --("dump-npy") "Write the iteration counts as NumPy array to a npy file. Takes the same placeholders as --output. Hint: Neither --workload, --stream, --tiles, --preview, --explore, --cost nor --validate is supported. Example: counts_{mechanism}.npy")
+        (@arg FromImage: +takes_value --("from-image") "Reproduce the render of a png written by this program. Its parameters are read from the metadata of the image, options given on the command line override them. Example: mandel.png")
+        (@arg Stream: --stream "Write the png band by band while it is computed, so the image never has to fit into memory. Hint: Only the png format without histogram is supported and neither --workload, --drawoff nor --dump-npy")
+        (@arg Tiles: +takes_value --tiles "Render a pyramid of 256x256 png tiles into a directory instead of one image. The directory holds the Deep Zoom descriptor tiles.dzi, which viewers like OpenSeadragon open, with the tiles in tiles_files/level/x_y.png, and an XYZ pyramid z/x/y.png for viewers like Leaflet. Takes the same placeholders as --output. Hint: The histogram is not supported and neither --workload nor --stream. Example: tiles_{mechanism}")
+        (@arg Zoom: +takes_value +allow_hyphen_values --zoom "Render an animation which zooms exponentially from the viewport to a target center. The frames are written to numbered files, the placeholder {frame} of --output and --dump-npy is replaced by the number of the frame or _{frame} is appended. If --output ends with .y4m or .gif, all frames are written into one video or animated GIF instead. An optional fourth value turns the last frame by that many degrees. For target = -0.743 + 0.1318 * I and a magnification of 1000 at the last frame, enter: -0.743,0.1318,1000")
+        (@arg Frames: +takes_value --frames "Specify the number of frames of the zoom. Default: 60")
+        (@arg Fps: +takes_value --fps "Specify the frames per second of a y4m or gif zoom. Default: 25")
+        (@arg Preview: --preview "Print a preview of the viewport in the terminal with 24 bit colours instead of writing an image. The preview is as large as the terminal and keeps the aspect ratio of the bounds. Hint: Neither --workload, --stream, --tiles nor --zoom is supported")
+        (@arg Explore: --explore "Explore the fractal interactively in the terminal, starting at the viewport. Arrow keys pan, + and - zoom, 1-5 choose the formula mandelbrot, burningship, tricorn, multibrot:3 or multibrot:4, 6-9 the palette gray, fire, ocean or ultra, c shows the --complexcoords of the view and q quits. Hint: Only one mechanism is supported and neither --workload, --stream, --tiles, --zoom nor --preview")
+        (@arg DrawOff: -d --drawoff "Disables writing the image to a file")
         //Unfortunately, this has to be written in a row, otherwise it will mess up the formatting
-        (@arg ComplexCoords: +takes_value -c --complexcoords "Specify an upper left and a lower right point on the complex plane.\nAttention: Enter a leading ',' because otherwise clap will interpret a '-' as a unknown argument.\nExample: For upper left = -1.6 + 1.2 * I and lower right = 0.6 - 1.2 * I, enter: ,-1.6,1.2,0.6,-1.2")
+        (@arg ComplexCoords: +takes_value +allow_hyphen_values -c --complexcoords "Specify an upper left and a lower right point on the complex plane. A leading ',' is still accepted.\nExample: For upper left = -1.6 + 1.2 * I and lower right = 0.6 - 1.2 * I, enter: -1.6,1.2,0.6,-1.2")
+        (@arg Center: +takes_value +allow_hyphen_values conflicts_with[ComplexCoords] --center "Specify the viewport by its center and a zoom instead of two corners. At zoom 1 the height is 4, the width follows from the bounds, so the pixels are square. Example: For center = -0.75 + 0.1 * I and zoom 50, enter: -0.75,0.1,50")
+        (@arg Deep: +takes_value +allow_hyphen_values conflicts_with[ComplexCoords Center] --deep "Zoom deeper than the precision of f64 allows, which ends at a zoom of about 1e13. Like --center, but the center may have up to 32 digits. Its orbit is computed with that precision and the pixels relative to it with f64, glitches are detected and rebased. Hint: Only the formula z² + c is supported and neither --shortcut, --periodicity, --zoom nor --explore. Example: -1.7499999999999999999999,0.0000000000000000000001,1e18")
+        (@arg Rotation: +takes_value +allow_hyphen_values --rotation "Turn the viewport counterclockwise around its center by an angle in degrees. Example: 30")
+        (@arg Square: --square "Grow the width or the height of the viewport around its center so that the pixels are square")
     ).get_matches();
 
-    let mechanism = matches.value_of("Mechanism").unwrap();
-
-    if !(mechanism.eq("all")
-        || mechanism.eq("threads")
-        || mechanism.eq("th")
-        || mechanism.eq("threadsunsafe")
-        || mechanism.eq("tu")
-        || mechanism.eq("crossbeam")
-        || mechanism.eq("cb")
-        || mechanism.eq("scoped_threadpool")
-        || mechanism.eq("st")
-        || mechanism.eq("rayon")
-        || mechanism.eq("ra"))
-    {
-        return Err(CustomError::InvalidArgument);
-    }
+    let text = match matches.value_of("FromImage") {
+        Some(v) => read_text(v)?,
+        None => vec![],
+    };
+
+    let mechanism = match value(&matches, &text, "Mechanism", "mechanism") {
+        Some(v) => v,
+        None => return Err(CustomError::InvalidArgument),
+    };
 
-    let bounds = match matches.value_of("Bounds") {
+    let bounds = match value(&matches, &text, "Bounds", "bounds") {
         Some(v) => {
             let split: Vec<&str> = v.split(',').collect();
             if split.len() < 2 {
@@ -65,33 +155,298 @@ pub fn parse_arguments() -> Result<ParsedArgs, CustomError> {
         None => BOUNDS,
     };
 
-    let threads = match matches.value_of("Threads") {
+    let threads = match value(&matches, &text, "Threads", "threads") {
         Some(v) => v.parse::<usize>()?,
         None => NTHREADS,
     };
 
-    let rows_per_band = match matches.value_of("Rows_per_band") {
+    let rows_per_band = match value(&matches, &text, "Rows_per_band", "rows") {
         Some(v) => v.parse::<usize>()?,
         None => ROWS_PER_BAND,
     };
 
-    let (upper_left, lower_right) = match matches.value_of("ComplexCoords") {
+    let max_iter = match value(&matches, &text, "MaxIter", "max-iter") {
+        Some(v) => v.parse::<u32>()?,
+        None => MAX_ITER,
+    };
+
+    if max_iter == 0 {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let julia = match value(&matches, &text, "Julia", "julia") {
         Some(v) => {
             let split: Vec<&str> = v.split(',').collect();
-            if split.len() < 5 {
+            if split.len() < 2 {
                 return Err(CustomError::InvalidArgument);
             }
-            let ul = Complex {
-                re: split[1].parse::<f64>()?,
-                im: split[2].parse::<f64>()?,
-            };
-            let ur = Complex {
-                re: split[3].parse::<f64>()?,
-                im: split[4].parse::<f64>()?,
+            Some(Complex {
+                re: split[0].parse::<f64>()?,
+                im: split[1].parse::<f64>()?,
+            })
+        }
+        None => None,
+    };
+
+    let formula = match value(&matches, &text, "Formula", "formula") {
+        Some(v) => parse_formula(v)?,
+        None => Arc::new(Mandelbrot),
+    };
+
+    let bailout = match value(&matches, &text, "Bailout", "bailout") {
+        Some(v) => v.parse::<f64>()?,
+        None => BAILOUT,
+    };
+
+    if !bailout.is_finite() || bailout < 2.0 {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let histogram = flag(&matches, &text, "Histogram", "histogram");
+
+    let gradient = match matches.value_of("Palette") {
+        Some(v) => match Gradient::builtin(v) {
+            Some(gradient) => Some(gradient),
+            None => Some(Gradient::from_file(v)?),
+        },
+        None => match stored(&text, "palette") {
+            Some(v) => Some(Gradient::parse(v)?),
+            None if histogram => Gradient::builtin("gray"),
+            None => None,
+        },
+    };
+
+    let colouring = match gradient {
+        Some(gradient) => {
+            let mut colouring = Colouring::new(gradient);
+            if let Some(v) = value(&matches, &text, "Cycle", "cycle") {
+                let cycle = v.parse::<f64>()?;
+                if cycle.is_nan() || cycle <= 0.0 {
+                    return Err(CustomError::InvalidArgument);
+                }
+                colouring.cycle = Some(cycle);
+            }
+            if let Some(v) = value(&matches, &text, "Offset", "offset") {
+                let offset = v.parse::<f64>()?;
+                if !offset.is_finite() {
+                    return Err(CustomError::InvalidArgument);
+                }
+                colouring.offset = offset;
+            }
+            if let Some(v) = value(&matches, &text, "Interior", "interior") {
+                colouring.interior = parse_colour(v)?;
+            }
+            if histogram {
+                colouring.mapping = Mapping::Histogram;
+            }
+            Some(colouring)
+        }
+        None => None,
+    };
+
+    let depth = match value(&matches, &text, "Depth", "depth") {
+        Some(v) => v.parse::<u8>()?,
+        None => 8,
+    };
+
+    if depth != 8 && depth != 16 {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let stream = matches.is_present("Stream");
+
+    if stream
+        && (matches.is_present("Measure")
+            || matches.is_present("DrawOff")
+            || matches.is_present("DumpNpy"))
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let tiles = matches.value_of("Tiles").map(|v| v.to_string());
+
+    if tiles.is_some() && (matches.is_present("Measure") || stream) {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let zoom = match matches.value_of("Zoom") {
+        Some(v) => {
+            let split: Vec<&str> = v.split(',').collect();
+            if split.len() < 3 {
+                return Err(CustomError::InvalidArgument);
+            }
+            let frames = match matches.value_of("Frames") {
+                Some(v) => v.parse::<usize>()?,
+                None => FRAMES,
             };
-            (ul, ur)
+            let factor = split[2].parse::<f64>()?;
+            if frames == 0 || !factor.is_finite() || factor <= 0.0 {
+                return Err(CustomError::InvalidArgument);
+            }
+            Some(Zoom {
+                target: Complex {
+                    re: split[0].parse::<f64>()?,
+                    im: split[1].parse::<f64>()?,
+                },
+                factor,
+                frames,
+                rotation: match split.get(3) {
+                    Some(v) => v.parse::<f64>()?,
+                    None => 0.0,
+                },
+            })
         }
-        None => (UPPER_LEFT, LOWER_RIGHT),
+        None => None,
+    };
+
+    let fps = match matches.value_of("Fps") {
+        Some(v) => v.parse::<f64>()?,
+        None => FPS,
+    };
+
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    if zoom.is_some() && (matches.is_present("Measure") || stream || tiles.is_some()) {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let preview = matches.is_present("Preview");
+
+    if preview && (matches.is_present("Measure") || stream || tiles.is_some() || zoom.is_some()) {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let explore = matches.is_present("Explore");
+
+    if explore
+        && (matches.is_present("Measure") || stream || tiles.is_some() || zoom.is_some() || preview)
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let cost = matches.is_present("Cost");
+
+    if cost
+        && (matches.is_present("Measure")
+            || stream
+            || tiles.is_some()
+            || zoom.is_some()
+            || preview
+            || explore)
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let strategy = match value(&matches, &text, "Strategy", "strategy") {
+        Some(v) => Strategy::parse(v)?,
+        None => Strategy::BruteForce,
+    };
+
+    let validate = matches.is_present("Validate");
+
+    if validate
+        && (matches.is_present("Measure")
+            || stream
+            || tiles.is_some()
+            || zoom.is_some()
+            || preview
+            || explore
+            || cost)
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    //Only the modes which compute a whole image write the array.
+    if matches.is_present("DumpNpy")
+        && (matches.is_present("Measure")
+            || tiles.is_some()
+            || preview
+            || explore
+            || cost
+            || validate)
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    let deep = match matches.value_of("Deep") {
+        Some(v) => {
+            let split: Vec<&str> = v.split(',').collect();
+            if split.len() != 3 {
+                return Err(CustomError::InvalidArgument);
+            }
+            let zoom = split[2].parse::<f64>()?;
+            if !zoom.is_finite() || zoom <= 0.0 {
+                return Err(CustomError::InvalidArgument);
+            }
+            Some((parse_point(&split[..2].join(","))?, zoom))
+        }
+        None => None,
+    };
+
+    //The pixels of a deep zoom are offsets from its center.
+    let mut viewport = if let Some((_, zoom)) = deep {
+        Viewport::from_center(Complex { re: 0.0, im: 0.0 }, zoom, bounds)
+    } else if let Some(v) = matches.value_of("Center") {
+        let split: Vec<&str> = v.split(',').collect();
+        if split.len() < 3 {
+            return Err(CustomError::InvalidArgument);
+        }
+        let zoom = split[2].parse::<f64>()?;
+        if !zoom.is_finite() || zoom <= 0.0 {
+            return Err(CustomError::InvalidArgument);
+        }
+        let center = Complex {
+            re: split[0].parse::<f64>()?,
+            im: split[1].parse::<f64>()?,
+        };
+        Viewport::from_center(center, zoom, bounds)
+    } else if let Some(v) = matches.value_of("ComplexCoords") {
+        Viewport::parse_corners(v)?
+    } else if let Some(v) = stored(&text, "complexcoords") {
+        Viewport::parse_corners(v)?
+    } else if julia.is_some() {
+        Viewport::from_corners(JULIA_UPPER_LEFT, JULIA_LOWER_RIGHT)
+    } else {
+        Viewport::from_corners(UPPER_LEFT, LOWER_RIGHT)
+    };
+
+    //The stored center of a deep zoom belongs to the stored viewport.
+    let deep = match deep {
+        Some((center, _)) => Some(center),
+        None if matches.is_present("Center") || matches.is_present("ComplexCoords") => None,
+        None => match stored(&text, "deep") {
+            Some(v) => Some(parse_point(v)?),
+            None => None,
+        },
+    };
+
+    let shortcut = flag(&matches, &text, "Shortcut", "shortcut");
+    let periodicity = flag(&matches, &text, "Periodicity", "periodicity");
+
+    //The reference orbit only knows z² + c and neither the shortcut nor periodicity.
+    if deep.is_some()
+        && (zoom.is_some() || explore || !formula.quadratic() || shortcut || periodicity)
+    {
+        return Err(CustomError::InvalidArgument);
+    }
+
+    if matches.is_present("Square") {
+        viewport = viewport.square(bounds);
+    }
+
+    if let Some(v) = value(&matches, &text, "Rotation", "rotation") {
+        let rotation = v.parse::<f64>()?;
+        if !rotation.is_finite() {
+            return Err(CustomError::InvalidArgument);
+        }
+        viewport = viewport.rotate(rotation);
+    }
+
+    let simd = match matches.value_of("Simd") {
+        Some(v) => Some(Lanes::parse(v)?),
+        None => None,
     };
 
     Ok(ParsedArgs {
@@ -100,8 +455,29 @@ pub fn parse_arguments() -> Result<ParsedArgs, CustomError> {
         bounds,
         threads,
         rows_per_band,
+        max_iter,
         draw: !matches.is_present("DrawOff"),
-        upper_left,
-        lower_right,
+        output: matches.value_of("Output").unwrap_or(OUTPUT).to_string(),
+        depth,
+        dump_npy: matches.value_of("DumpNpy").map(|v| v.to_string()),
+        stream,
+        tiles,
+        zoom,
+        fps,
+        preview,
+        explore,
+        viewport,
+        deep,
+        julia,
+        formula,
+        bailout,
+        smooth: flag(&matches, &text, "Smooth", "smooth"),
+        shortcut,
+        periodicity,
+        simd,
+        cost,
+        strategy,
+        validate,
+        colouring,
     })
 }
@@ -0,0 +1,330 @@
+use crate::customerror::CustomError;
+use num::Complex;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+///The number of significant digits a DoubleDouble is written with.
+static DIGITS: usize = 32;
+
+///The largest exponent parse accepts, a little beyond the range of f64.
+static MAX_EXPONENT: i32 = 330;
+
+///A float with about 32 significant digits, the unevaluated sum of two f64 values. The part lo
+///holds what hi can't represent, so |lo| is at most half an ulp of hi.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    ///Returns the DoubleDouble of an f64.
+    pub fn new(value: f64) -> Self {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    ///Returns the exact sum of a and b as DoubleDouble.
+    fn two_sum(a: f64, b: f64) -> Self {
+        let hi = a + b;
+        let b_part = hi - a;
+        let lo = (a - (hi - b_part)) + (b - b_part);
+        DoubleDouble { hi, lo }
+    }
+
+    ///Like two_sum, but only exact if |a| >= |b|.
+    fn quick_two_sum(a: f64, b: f64) -> Self {
+        let hi = a + b;
+        DoubleDouble {
+            hi,
+            lo: b - (hi - a),
+        }
+    }
+
+    ///Returns the value rounded to the nearest f64.
+    pub fn to_f64(&self) -> f64 {
+        self.hi + self.lo
+    }
+
+    ///Returns 10 to the power of exponent by squaring and multiplying.
+    fn pow10(exponent: i32) -> Self {
+        let mut power = DoubleDouble::new(1.0);
+        let mut square = DoubleDouble::new(10.0);
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                power = power * square;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                square = square * square;
+            }
+        }
+        if exponent < 0 {
+            DoubleDouble::new(1.0) / power
+        } else {
+            power
+        }
+    }
+
+    ///Parses a decimal number like f64 does, but keeps all digits up to the precision of a
+    ///DoubleDouble. Infinity, NaN, an exponent beyond ±MAX_EXPONENT and numbers which don't fit
+    ///into a DoubleDouble are an invalid argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The decimal number, for example -0.7436438870371587047521915061147 or 1.5e-20.
+    pub fn parse(value: &str) -> Result<Self, CustomError> {
+        //The parser of f64 checks the syntax.
+        value.parse::<f64>()?;
+        let (mantissa, mut exponent) = match value.find(['e', 'E']) {
+            Some(i) => (&value[..i], value[i + 1..].parse::<i32>()?),
+            None => (value, 0),
+        };
+        if !(-MAX_EXPONENT..=MAX_EXPONENT).contains(&exponent) {
+            return Err(CustomError::InvalidArgument);
+        }
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, mantissa.trim_start_matches('+')),
+        };
+        let mut result = DoubleDouble::default();
+        let mut fraction = false;
+        for c in mantissa.chars() {
+            if c == '.' {
+                fraction = true;
+                continue;
+            }
+            let digit = c.to_digit(10).ok_or(CustomError::InvalidArgument)?;
+            result = result * DoubleDouble::new(10.0) + DoubleDouble::new(digit as f64);
+            if fraction {
+                exponent -= 1;
+            }
+        }
+        let result = if exponent < 0 {
+            result / DoubleDouble::pow10(-exponent)
+        } else {
+            result * DoubleDouble::pow10(exponent)
+        };
+        if !result.hi.is_finite() {
+            return Err(CustomError::InvalidArgument);
+        }
+        Ok(if negative { -result } else { result })
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let sum = DoubleDouble::two_sum(self.hi, other.hi);
+        DoubleDouble::quick_two_sum(sum.hi, sum.lo + self.lo + other.lo)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let hi = self.hi * other.hi;
+        //The fused multiply add returns the rounding error of the product exactly.
+        let lo = self.hi.mul_add(other.hi, -hi) + self.hi * other.lo + self.lo * other.hi;
+        DoubleDouble::quick_two_sum(hi, lo)
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    ///Divides by long division.
+    fn div(self, divisor: Self) -> Self {
+        let q1 = self.hi / divisor.hi;
+        let r = self - divisor * DoubleDouble::new(q1);
+        let q2 = r.hi / divisor.hi;
+        let r = r - divisor * DoubleDouble::new(q2);
+        let q3 = r.hi / divisor.hi;
+        DoubleDouble::quick_two_sum(q1, q2) + DoubleDouble::new(q3)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl fmt::Display for DoubleDouble {
+    ///Writes the number in scientific notation with DIGITS significant digits, which parse reads
+    ///back.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.hi == 0.0 || !self.hi.is_finite() {
+            return write!(f, "{}", self.hi);
+        }
+        let negative = self.hi < 0.0;
+        let mut x = if negative { -*self } else { *self };
+        let mut exponent = x.hi.log10().floor() as i32;
+        x = x / DoubleDouble::pow10(exponent);
+        //The logarithm can be off by one close to a power of ten.
+        if x.hi >= 10.0 {
+            x = x / DoubleDouble::new(10.0);
+            exponent += 1;
+        } else if x.hi < 1.0 {
+            x = x * DoubleDouble::new(10.0);
+            exponent -= 1;
+        }
+        let mut digits = String::new();
+        for _ in 0..DIGITS {
+            let mut digit = x.hi.floor();
+            if (x - DoubleDouble::new(digit)).hi < 0.0 {
+                digit -= 1.0;
+            }
+            digits.push(std::char::from_digit(digit.clamp(0.0, 9.0) as u32, 10).unwrap());
+            x = (x - DoubleDouble::new(digit)) * DoubleDouble::new(10.0);
+        }
+        let digits = digits.trim_end_matches('0');
+        let (first, rest) = digits.split_at(1);
+        let sign = if negative { "-" } else { "" };
+        if rest.is_empty() {
+            write!(f, "{}{}e{}", sign, first, exponent)
+        } else {
+            write!(f, "{}{}.{}e{}", sign, first, rest, exponent)
+        }
+    }
+}
+
+///Parses a point of the complex plane as two comma separated DoubleDoubles.
+///
+/// # Arguments
+///
+/// * `value` - The real and the imaginary part, for example -1.749,0.0000000000000000000001.
+pub fn parse_point(value: &str) -> Result<(DoubleDouble, DoubleDouble), CustomError> {
+    let split: Vec<&str> = value.split(',').collect();
+    if split.len() != 2 {
+        return Err(CustomError::InvalidArgument);
+    }
+    Ok((
+        DoubleDouble::parse(split[0])?,
+        DoubleDouble::parse(split[1])?,
+    ))
+}
+
+///The orbit of the center of a deep zoom computed with DoubleDoubles, which the orbits of all
+///pixels are computed relative to with f64, see Reference::escape.
+///
+///A pixel is given by its offset from the center, which f64 holds with full precision even when
+///the center itself needs more digits than f64 has. This is the perturbation method: instead of
+///z, only the difference d to the reference orbit Z is iterated with
+///d' = (2Z + d)d + dc, which follows from z² + c.
+#[derive(Debug)]
+pub struct Reference {
+    ///The center of the image and the point all offsets are relative to.
+    pub center: (DoubleDouble, DoubleDouble),
+    ///Decides whether the offsets are start values of a Julia set instead of parameters.
+    pub julia: bool,
+    ///The values of the orbit rounded to f64, starting with the start value. The last one is the
+    ///escaped value or the one after max_iter iterations.
+    orbit: Vec<Complex<f64>>,
+}
+
+impl Reference {
+    ///Computes the orbit of the center with z² + c.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The center of the image.
+    /// * `julia` - If set, the center starts the orbit of the Julia set of this parameter.
+    /// * `max_iter` - The maximum number of iterations.
+    /// * `bailout` - The radius of the circle the orbit has to leave.
+    pub fn new(
+        center: (DoubleDouble, DoubleDouble),
+        julia: Option<Complex<f64>>,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Self {
+        let zero = DoubleDouble::default();
+        let (mut z, c) = match julia {
+            Some(c) => (center, (DoubleDouble::new(c.re), DoubleDouble::new(c.im))),
+            None => ((zero, zero), center),
+        };
+        let to_complex = |z: (DoubleDouble, DoubleDouble)| Complex::new(z.0.to_f64(), z.1.to_f64());
+        let mut orbit = vec![to_complex(z)];
+        for _ in 0..max_iter {
+            let (re, im) = z;
+            z = (
+                re * re - im * im + c.0,
+                re * im * DoubleDouble::new(2.0) + c.1,
+            );
+            orbit.push(to_complex(z));
+            if orbit[orbit.len() - 1].norm_sqr() > bailout * bailout {
+                break;
+            }
+        }
+        Reference {
+            center,
+            julia: julia.is_some(),
+            orbit,
+        }
+    }
+
+    ///Returns the number of iterations of the reference orbit.
+    pub fn iterations(&self) -> usize {
+        self.orbit.len() - 1
+    }
+
+    ///Like Kernel::escape for the point at an offset from the center, see Reference. Also returns
+    ///how often the orbit was rebased.
+    ///
+    ///The difference loses its precision if the orbit comes closer to the start of the reference
+    ///than to the reference itself, which shows as a glitch: a blob of pixels with the same count.
+    ///It is detected by comparing both distances. The orbit is then rebased, it continues
+    ///with the difference to the start of the reference, which is the same as iterating the
+    ///reference from the beginning. It is rebased as well when the reference escaped before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the point from the center.
+    /// * `max_iter` - The maximum number of iterations.
+    /// * `bailout` - The radius of the circle the orbit has to leave.
+    pub fn escape(
+        &self,
+        offset: Complex<f64>,
+        max_iter: u32,
+        bailout: f64,
+    ) -> (Option<(u32, Complex<f64>)>, u32) {
+        let bailout_sqr = bailout * bailout;
+        let zero = Complex::new(0.0, 0.0);
+        let (mut d, dc) = if self.julia {
+            (offset, zero)
+        } else {
+            (zero, offset)
+        };
+        let start = self.orbit[0];
+        let (mut n, mut rebases) = (0, 0);
+        for i in 0..max_iter {
+            d = (self.orbit[n] * 2.0 + d) * d + dc;
+            n += 1;
+            let z = self.orbit[n] + d;
+            if z.norm_sqr() > bailout_sqr {
+                return (Some((i, z)), rebases);
+            }
+            if (z - start).norm_sqr() < d.norm_sqr() || n == self.orbit.len() - 1 {
+                d = z - start;
+                n = 0;
+                rebases += 1;
+            }
+        }
+        (None, rebases)
+    }
+}
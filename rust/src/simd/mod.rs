@@ -0,0 +1,273 @@
+use crate::customerror::CustomError;
+use num::Complex;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+///The instruction sets the vectorised kernel iterates a group of points with, see iterate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lanes {
+    ///Portable code on groups of four points, which needs no special instructions.
+    Scalar,
+    ///Two points per 128 bit register.
+    Sse2,
+    ///Four points per 256 bit register.
+    Avx2,
+    ///Eight points per 512 bit register.
+    Avx512,
+}
+
+impl Lanes {
+    ///Returns the widest lanes the processor supports.
+    pub fn detect() -> Self {
+        *[Lanes::Avx512, Lanes::Avx2, Lanes::Sse2]
+            .iter()
+            .find(|lanes| lanes.supported())
+            .unwrap_or(&Lanes::Scalar)
+    }
+
+    ///Parses the lanes from their name, see name. Auto picks the widest supported lanes. Lanes the
+    ///processor doesn't support are an invalid argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the lanes or auto.
+    pub fn parse(name: &str) -> Result<Self, CustomError> {
+        let lanes = match name {
+            "auto" => Lanes::detect(),
+            "scalar" => Lanes::Scalar,
+            "sse2" => Lanes::Sse2,
+            "avx2" => Lanes::Avx2,
+            "avx512" => Lanes::Avx512,
+            _ => return Err(CustomError::InvalidArgument),
+        };
+        if lanes.supported() {
+            Ok(lanes)
+        } else {
+            Err(CustomError::InvalidArgument)
+        }
+    }
+
+    ///Returns the name which selects the lanes on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lanes::Scalar => "scalar",
+            Lanes::Sse2 => "sse2",
+            Lanes::Avx2 => "avx2",
+            Lanes::Avx512 => "avx512",
+        }
+    }
+
+    ///Returns the number of points which are iterated together.
+    pub fn width(&self) -> usize {
+        match self {
+            Lanes::Scalar => 4,
+            Lanes::Sse2 => 2,
+            Lanes::Avx2 => 4,
+            Lanes::Avx512 => 8,
+        }
+    }
+
+    ///Returns true if the processor running the program has the instructions of the lanes.
+    #[cfg(target_arch = "x86_64")]
+    pub fn supported(&self) -> bool {
+        match self {
+            Lanes::Scalar => true,
+            Lanes::Sse2 => is_x86_feature_detected!("sse2"),
+            Lanes::Avx2 => is_x86_feature_detected!("avx2"),
+            Lanes::Avx512 => is_x86_feature_detected!("avx512f"),
+        }
+    }
+
+    ///Returns true if the processor running the program has the instructions of the lanes.
+    #[cfg(not(target_arch = "x86_64"))]
+    pub fn supported(&self) -> bool {
+        *self == Lanes::Scalar
+    }
+}
+
+///The orbits of up to eight points which are iterated together. Lanes beyond the width of the
+///group repeat its last point.
+struct Group {
+    zr: [f64; 8],
+    zi: [f64; 8],
+    cr: [f64; 8],
+    ci: [f64; 8],
+    ///The number of iterations before the orbit escaped, as float to fit the registers.
+    counts: [f64; 8],
+}
+
+///Iterates z² + c for a row of points and stores the iteration counts like Kernel::iterations,
+///so an escaped point holds the iteration it escaped in and a member `max_iter`.
+///
+///The points are iterated in groups as wide as the lanes. Every operation is the same as the one
+///num::Complex performs in the same order, so the counts are identical to the ones of the
+///Mandelbrot formula. Lanes the processor doesn't support fall back to Lanes::Scalar.
+///
+/// # Arguments
+///
+/// * `lanes` - The instruction set which iterates the groups.
+/// * `points` - The points of the row.
+/// * `julia` - If set, the points start the orbits of the Julia set of this parameter.
+/// * `max_iter` - The maximum number of iterations per point.
+/// * `bailout` - The radius of the circle the orbit has to leave.
+/// * `counts` - A buffer which receives one iteration count per point.
+pub fn iterate(
+    lanes: Lanes,
+    points: &[Complex<f64>],
+    julia: Option<Complex<f64>>,
+    max_iter: u32,
+    bailout: f64,
+    counts: &mut [u32],
+) {
+    let lanes = if lanes.supported() {
+        lanes
+    } else {
+        Lanes::Scalar
+    };
+    let bailout_sqr = bailout * bailout;
+    let width = lanes.width();
+    for (points, counts) in points.chunks(width).zip(counts.chunks_mut(width)) {
+        let mut group = Group {
+            zr: [0.0; 8],
+            zi: [0.0; 8],
+            cr: [0.0; 8],
+            ci: [0.0; 8],
+            counts: [0.0; 8],
+        };
+        for lane in 0..8 {
+            let point = points[lane.min(points.len() - 1)];
+            let (z, c) = match julia {
+                Some(c) => (point, c),
+                None => (Complex { re: 0.0, im: 0.0 }, point),
+            };
+            group.zr[lane] = z.re;
+            group.zi[lane] = z.im;
+            group.cr[lane] = c.re;
+            group.ci[lane] = c.im;
+        }
+
+        //Safe, because unsupported lanes were replaced by Lanes::Scalar above.
+        match lanes {
+            Lanes::Scalar => iterate_scalar(&mut group, max_iter, bailout_sqr),
+            #[cfg(target_arch = "x86_64")]
+            Lanes::Sse2 => unsafe { iterate_sse2(&mut group, max_iter, bailout_sqr) },
+            #[cfg(target_arch = "x86_64")]
+            Lanes::Avx2 => unsafe { iterate_avx2(&mut group, max_iter, bailout_sqr) },
+            #[cfg(target_arch = "x86_64")]
+            Lanes::Avx512 => unsafe { iterate_avx512(&mut group, max_iter, bailout_sqr) },
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => unreachable!(),
+        }
+
+        for (count, value) in counts.iter_mut().zip(group.counts.iter()) {
+            *count = *value as u32;
+        }
+    }
+}
+
+///Iterates the first four lanes of a group without special instructions.
+fn iterate_scalar(group: &mut Group, max_iter: u32, bailout_sqr: f64) {
+    let mut active = [true; 4];
+    for _ in 0..max_iter {
+        for (lane, active) in active.iter_mut().enumerate().filter(|(_, active)| **active) {
+            let (re, im) = (group.zr[lane], group.zi[lane]);
+            let reim = re * im;
+            group.zr[lane] = re * re - im * im + group.cr[lane];
+            group.zi[lane] = reim + reim + group.ci[lane];
+            let norm = group.zr[lane] * group.zr[lane] + group.zi[lane] * group.zi[lane];
+            //An orbit which became NaN never escapes, just like in Formula::escape.
+            if norm > bailout_sqr {
+                *active = false;
+            } else {
+                group.counts[lane] += 1.0;
+            }
+        }
+        if active == [false; 4] {
+            break;
+        }
+    }
+}
+
+///Iterates the first two lanes of a group with SSE2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn iterate_sse2(group: &mut Group, max_iter: u32, bailout_sqr: f64) {
+    let mut zr = _mm_loadu_pd(group.zr.as_ptr());
+    let mut zi = _mm_loadu_pd(group.zi.as_ptr());
+    let cr = _mm_loadu_pd(group.cr.as_ptr());
+    let ci = _mm_loadu_pd(group.ci.as_ptr());
+    let bailout = _mm_set1_pd(bailout_sqr);
+    let one = _mm_set1_pd(1.0);
+    let mut active = _mm_castsi128_pd(_mm_set1_epi64x(-1));
+    let mut counts = _mm_setzero_pd();
+    for _ in 0..max_iter {
+        let reim = _mm_mul_pd(zr, zi);
+        zr = _mm_add_pd(_mm_sub_pd(_mm_mul_pd(zr, zr), _mm_mul_pd(zi, zi)), cr);
+        zi = _mm_add_pd(_mm_add_pd(reim, reim), ci);
+        let norm = _mm_add_pd(_mm_mul_pd(zr, zr), _mm_mul_pd(zi, zi));
+        active = _mm_and_pd(active, _mm_cmpngt_pd(norm, bailout));
+        if _mm_movemask_pd(active) == 0 {
+            break;
+        }
+        counts = _mm_add_pd(counts, _mm_and_pd(active, one));
+    }
+    _mm_storeu_pd(group.counts.as_mut_ptr(), counts);
+}
+
+///Iterates the first four lanes of a group with AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn iterate_avx2(group: &mut Group, max_iter: u32, bailout_sqr: f64) {
+    let mut zr = _mm256_loadu_pd(group.zr.as_ptr());
+    let mut zi = _mm256_loadu_pd(group.zi.as_ptr());
+    let cr = _mm256_loadu_pd(group.cr.as_ptr());
+    let ci = _mm256_loadu_pd(group.ci.as_ptr());
+    let bailout = _mm256_set1_pd(bailout_sqr);
+    let one = _mm256_set1_pd(1.0);
+    let mut active = _mm256_castsi256_pd(_mm256_set1_epi64x(-1));
+    let mut counts = _mm256_setzero_pd();
+    for _ in 0..max_iter {
+        let reim = _mm256_mul_pd(zr, zi);
+        zr = _mm256_add_pd(
+            _mm256_sub_pd(_mm256_mul_pd(zr, zr), _mm256_mul_pd(zi, zi)),
+            cr,
+        );
+        zi = _mm256_add_pd(_mm256_add_pd(reim, reim), ci);
+        let norm = _mm256_add_pd(_mm256_mul_pd(zr, zr), _mm256_mul_pd(zi, zi));
+        active = _mm256_and_pd(active, _mm256_cmp_pd::<_CMP_NGT_UQ>(norm, bailout));
+        if _mm256_movemask_pd(active) == 0 {
+            break;
+        }
+        counts = _mm256_add_pd(counts, _mm256_and_pd(active, one));
+    }
+    _mm256_storeu_pd(group.counts.as_mut_ptr(), counts);
+}
+
+///Iterates all eight lanes of a group with AVX-512.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn iterate_avx512(group: &mut Group, max_iter: u32, bailout_sqr: f64) {
+    let mut zr = _mm512_loadu_pd(group.zr.as_ptr());
+    let mut zi = _mm512_loadu_pd(group.zi.as_ptr());
+    let cr = _mm512_loadu_pd(group.cr.as_ptr());
+    let ci = _mm512_loadu_pd(group.ci.as_ptr());
+    let bailout = _mm512_set1_pd(bailout_sqr);
+    let one = _mm512_set1_pd(1.0);
+    let mut active: __mmask8 = 0xff;
+    let mut counts = _mm512_setzero_pd();
+    for _ in 0..max_iter {
+        let reim = _mm512_mul_pd(zr, zi);
+        zr = _mm512_add_pd(
+            _mm512_sub_pd(_mm512_mul_pd(zr, zr), _mm512_mul_pd(zi, zi)),
+            cr,
+        );
+        zi = _mm512_add_pd(_mm512_add_pd(reim, reim), ci);
+        let norm = _mm512_add_pd(_mm512_mul_pd(zr, zr), _mm512_mul_pd(zi, zi));
+        active = _mm512_mask_cmp_pd_mask::<_CMP_NGT_UQ>(active, norm, bailout);
+        if active == 0 {
+            break;
+        }
+        counts = _mm512_mask_add_pd(counts, active, counts, one);
+    }
+    _mm512_storeu_pd(group.counts.as_mut_ptr(), counts);
+}
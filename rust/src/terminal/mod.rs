@@ -0,0 +1,419 @@
+use crate::customerror::CustomError;
+use crate::formula::parse_formula;
+use crate::mandel::Buffer;
+use crate::mechanism::{Mechanism, RenderJob, RenderResult};
+use crate::palette::{rgb, Colouring, Gradient};
+use crate::viewport::Viewport;
+use num::Complex;
+use std::env;
+use std::fmt::Write as _;
+use std::io;
+use std::io::prelude::*;
+
+///The size of the terminal if it can't be determined.
+static FALLBACK_SIZE: (usize, usize) = (80, 24);
+///The formulas of the number keys 1 to 5 in the explorer.
+static FORMULAS: [&str; 5] = [
+    "mandelbrot",
+    "burningship",
+    "tricorn",
+    "multibrot:3",
+    "multibrot:4",
+];
+///The palettes of the number keys 6 to 9 in the explorer.
+static PALETTES: [&str; 4] = ["gray", "fire", "ocean", "ultra"];
+///The share of the viewport an arrow key pans.
+static PAN: f64 = 0.1;
+///The factor + and - zoom in and out.
+static ZOOM: f64 = 2.0;
+///The help in the status line of the explorer.
+static HELP: &str = "arrows pan, +/- zoom, 1-5 formula, 6-9 palette, c coords, q quit";
+
+///Returns the number of columns and rows of the terminal. Asks the terminal of stdout first,
+///then the environment variables COLUMNS and LINES, and falls back to 80x24.
+pub fn terminal_size() -> (usize, usize) {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+        && size.ws_col > 0
+        && size.ws_row > 0
+    {
+        return (size.ws_col as usize, size.ws_row as usize);
+    }
+    let variable = |name: &str| env::var(name).ok().and_then(|v| v.parse::<usize>().ok());
+    match (variable("COLUMNS"), variable("LINES")) {
+        (Some(columns), Some(rows)) if columns > 0 && rows > 0 => (columns, rows),
+        _ => FALLBACK_SIZE,
+    }
+}
+
+///Returns the number of columns and rows of half-block characters which show an image as large
+///as possible in an area of the terminal without distorting it. A character shows two pixels,
+///one above the other, which are about square.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+/// * `area` - The number of columns and rows available.
+pub fn fit(bounds: (usize, usize), area: (usize, usize)) -> (usize, usize) {
+    let (width, height) = (bounds.0.max(1) as f64, bounds.1.max(1) as f64);
+    let scale = (area.0 as f64 / width).min(2.0 * area.1 as f64 / height);
+    let columns = ((width * scale).round() as usize).clamp(1, area.0.max(1));
+    let rows = ((height * scale / 2.0).round() as usize).clamp(1, area.1.max(1));
+    (columns, rows)
+}
+
+///Returns the first and the end of the pixels covered by cell i of n cells over len pixels.
+///Every cell covers at least one pixel.
+fn span(i: usize, n: usize, len: usize) -> (usize, usize) {
+    let start = i * len / n;
+    (start, ((i + 1) * len / n).clamp(start + 1, len))
+}
+
+///Downsamples a buffer to a grid of half-block characters with 24 bit ANSI colours. The colour of
+///a half-block is the average colour of the pixels it covers. Every line ends with a reset of the
+///colours and a newline.
+///
+/// # Arguments
+///
+/// * `pixels` - A buffer holding the iteration count of one pixel per element.
+/// * `bounds` - The dimensions of the image.
+/// * `size` - The number of columns and rows of characters, see fit.
+/// * `max_iter` - The iteration limit the buffer was rendered with.
+/// * `colouring` - Maps the iteration counts to colours. If None, the preview is grayscale.
+pub fn to_ansi(
+    pixels: &Buffer,
+    bounds: (usize, usize),
+    size: (usize, usize),
+    max_iter: u32,
+    colouring: Option<&Colouring>,
+) -> Result<String, CustomError> {
+    if pixels.len() != bounds.0 * bounds.1 || pixels.is_empty() {
+        return Err(CustomError::UnfittingArray);
+    }
+    let rgb = rgb(pixels, max_iter, colouring);
+    //The average colour of the pixels covered by the half-block in column x and half row y.
+    let average = |x: usize, y: usize| -> [u32; 3] {
+        let (left, right) = span(x, size.0, bounds.0);
+        let (top, bottom) = span(y, 2 * size.1, bounds.1);
+        let mut sum = [0u32; 3];
+        for row in top..bottom {
+            for column in left..right {
+                let i = (row * bounds.0 + column) * 3;
+                for channel in 0..3 {
+                    sum[channel] += rgb[i + channel] as u32;
+                }
+            }
+        }
+        let count = ((bottom - top) * (right - left)) as u32;
+        [sum[0] / count, sum[1] / count, sum[2] / count]
+    };
+
+    let mut ansi = String::new();
+    for row in 0..size.1 {
+        for column in 0..size.0 {
+            let upper = average(column, 2 * row);
+            let lower = average(column, 2 * row + 1);
+            //The upper half block is drawn in the foreground colour on the background colour.
+            write!(
+                ansi,
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                upper[0], upper[1], upper[2], lower[0], lower[1], lower[2]
+            )
+            .unwrap();
+        }
+        ansi.push_str("\x1b[0m\n");
+    }
+    Ok(ansi)
+}
+
+///Renders a preview of the image of job with mechanism, which fills an area of the terminal.
+///The viewport of job is rendered at twice the resolution of the half-blocks in both directions
+///and downsampled, see to_ansi. Returns the preview and the result of the render.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computes the preview.
+/// * `job` - The image to preview. Its bounds only decide the aspect ratio.
+/// * `area` - The number of columns and rows available.
+/// * `colouring` - Maps the iteration counts to colours. If None, the preview is grayscale.
+pub fn preview(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    area: (usize, usize),
+    colouring: Option<&Colouring>,
+) -> Result<(String, RenderResult), CustomError> {
+    let size = fit(job.bounds, area);
+    let job = RenderJob {
+        bounds: (2 * size.0, 4 * size.1),
+        ..job.clone()
+    };
+    let result = mechanism.run(&job)?;
+    let ansi = to_ansi(
+        &result.pixels,
+        job.bounds,
+        size,
+        job.kernel.max_iter,
+        colouring,
+    )?;
+    Ok((ansi, result))
+}
+
+///A key pressed in the explorer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+///Decodes the bytes read from a terminal in raw mode into keys. The arrow keys are sent as the
+///escape sequences ESC [ A to ESC [ D, other escape sequences are skipped.
+///
+/// # Arguments
+///
+/// * `input` - The bytes read from the terminal.
+pub fn parse_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == 0x1b && input.get(i + 1) == Some(&b'[') {
+            match input.get(i + 2) {
+                Some(b'A') => keys.push(Key::Up),
+                Some(b'B') => keys.push(Key::Down),
+                Some(b'C') => keys.push(Key::Right),
+                Some(b'D') => keys.push(Key::Left),
+                _ => {}
+            }
+            i += 3;
+        } else {
+            if input[i].is_ascii() && input[i] != 0x1b {
+                keys.push(Key::Char(input[i] as char));
+            }
+            i += 1;
+        }
+    }
+    keys
+}
+
+///What the explorer does after a key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    ///The viewport or the colours changed, so the preview is rendered again.
+    Render,
+    ///Shows a message in the status line.
+    Show(String),
+    ///Leaves the explorer.
+    Quit,
+    ///The key has no meaning.
+    Nothing,
+}
+
+///The state of the interactive explorer, see explore.
+pub struct Explorer {
+    ///The image which is previewed. Its viewport and formula change with the keys.
+    pub job: RenderJob,
+    ///Maps the iteration counts to colours. If None, the preview is grayscale.
+    pub colouring: Option<Colouring>,
+    ///The name of the palette chosen with a number key.
+    pub palette: Option<&'static str>,
+}
+
+impl Explorer {
+    ///Returns an Explorer which starts at the viewport of job.
+    pub fn new(job: RenderJob, colouring: Option<Colouring>) -> Self {
+        Explorer {
+            job,
+            colouring,
+            palette: None,
+        }
+    }
+
+    ///Changes the state by a key and returns what to do next.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key which was pressed.
+    pub fn handle(&mut self, key: Key) -> Result<Action, CustomError> {
+        let viewport = &mut self.job.viewport;
+        let (width, height) = (viewport.width(), viewport.height());
+        let shift = match key {
+            Key::Up => Some(Complex::new(0.0, height * PAN)),
+            Key::Down => Some(Complex::new(0.0, -height * PAN)),
+            Key::Left => Some(Complex::new(-width * PAN, 0.0)),
+            Key::Right => Some(Complex::new(width * PAN, 0.0)),
+            _ => None,
+        };
+        if let Some(mut shift) = shift {
+            //The arrows pan along the edges of the screen, which are turned with the viewport.
+            if viewport.rotation != 0.0 {
+                let (sin, cos) = viewport.rotation.to_radians().sin_cos();
+                shift *= Complex::new(cos, sin);
+            }
+            viewport.upper_left += shift;
+            viewport.lower_right += shift;
+            return Ok(Action::Render);
+        }
+        let key = match key {
+            Key::Char(c) => c,
+            _ => return Ok(Action::Nothing),
+        };
+        match key {
+            '+' | '=' | '-' => {
+                let scale = if key == '-' { ZOOM } else { 1.0 / ZOOM };
+                //The zoom keeps the angle of the viewport.
+                *viewport = Viewport {
+                    rotation: viewport.rotation,
+                    ..Viewport::from_size(viewport.center(), width * scale, height * scale)
+                };
+                Ok(Action::Render)
+            }
+            '1'..='5' => {
+                let name = FORMULAS[key as usize - '1' as usize];
+                self.job.kernel.formula = parse_formula(name)?;
+                Ok(Action::Render)
+            }
+            '6'..='9' => {
+                let name = PALETTES[key as usize - '6' as usize];
+                //Builtin only returns None for unknown names.
+                let gradient = Gradient::builtin(name).unwrap();
+                match self.colouring {
+                    Some(ref mut colouring) => colouring.gradient = gradient,
+                    None => self.colouring = Some(Colouring::new(gradient)),
+                }
+                self.palette = Some(name);
+                Ok(Action::Render)
+            }
+            'c' => Ok(Action::Show(viewport.arguments())),
+            'q' | '\x03' => Ok(Action::Quit),
+            _ => Ok(Action::Nothing),
+        }
+    }
+
+    ///Returns the status line after a render, which is cut to the width of the terminal.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the mechanism.
+    /// * `time` - The time in ms the render took.
+    /// * `message` - Replaces the help if set.
+    /// * `columns` - The width of the terminal.
+    pub fn status(&self, label: &str, time: f64, message: Option<&str>, columns: usize) -> String {
+        let palette = match (self.palette, &self.colouring) {
+            (Some(name), _) => name,
+            (None, Some(_)) => "custom",
+            (None, None) => "grayscale",
+        };
+        let status = format!(
+            "{} {:.1}ms | {} | {} | {}",
+            label,
+            time,
+            self.job.kernel.formula.name(),
+            palette,
+            message.unwrap_or(HELP)
+        );
+        status.chars().take(columns).collect()
+    }
+}
+
+///Switches the terminal of stdin to raw mode, in which every key is read at once without echo,
+///and restores the previous mode when it is dropped.
+struct RawMode {
+    previous: libc::termios,
+}
+
+impl RawMode {
+    fn new() -> Result<Self, CustomError> {
+        let mut previous: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut previous) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut raw = previous;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(RawMode { previous })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.previous) };
+    }
+}
+
+///Explores the fractal interactively in the terminal. The preview is rendered with mechanism
+///after every change and fills the terminal except for a status line with the time of the
+///render. Returns when q is pressed.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computes the previews.
+/// * `explorer` - The state of the explorer, which holds the viewport at the end.
+pub fn explore(mechanism: &dyn Mechanism, explorer: &mut Explorer) -> Result<(), CustomError> {
+    let _raw = RawMode::new()?;
+    let mut stdout = io::stdout();
+    let mut stdin = io::stdin();
+    //The alternate screen keeps the scrollback of the terminal, the cursor is hidden.
+    stdout.write_all(b"\x1b[?1049h\x1b[?25l")?;
+    let result = (|| -> Result<(), CustomError> {
+        let mut action = Action::Render;
+        let mut last = (String::new(), 0.0);
+        let mut buffer = [0u8; 64];
+        loop {
+            let (columns, rows) = terminal_size();
+            let message = match action {
+                Action::Render => {
+                    let (ansi, result) = preview(
+                        mechanism,
+                        &explorer.job,
+                        (columns, rows.saturating_sub(1).max(1)),
+                        explorer.colouring.as_ref(),
+                    )?;
+                    last = (ansi, result.time);
+                    None
+                }
+                Action::Show(ref message) => Some(message.as_str()),
+                Action::Quit => return Ok(()),
+                Action::Nothing => None,
+            };
+            if action != Action::Nothing {
+                write!(
+                    stdout,
+                    "\x1b[H\x1b[2J{}{}",
+                    last.0.replace('\n', "\r\n"),
+                    explorer.status(mechanism.label(), last.1, message, columns)
+                )?;
+                stdout.flush()?;
+            }
+            let read = stdin.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            action = Action::Nothing;
+            for key in parse_keys(&buffer[..read]) {
+                match explorer.handle(key)? {
+                    Action::Nothing => {}
+                    //A render or quit wins over a message of an earlier key.
+                    next => {
+                        if action != Action::Render || next == Action::Quit {
+                            action = next
+                        }
+                    }
+                }
+            }
+        }
+    })();
+    stdout.write_all(b"\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    result
+}
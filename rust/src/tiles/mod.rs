@@ -0,0 +1,221 @@
+use crate::customerror::CustomError;
+use crate::mandel::write_image;
+use crate::mechanism::{Mechanism, RenderJob};
+use crate::palette::{Colouring, Mapping};
+use crate::time::{Clock, MyTimestamp};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///The width and height of a tile in pixels.
+pub const TILE_SIZE: usize = 256;
+
+///The name of the Deep Zoom descriptor without extension, which also names the directory of the
+///tiles next to it.
+pub const NAME: &str = "tiles";
+
+///A tile of the pyramid.
+pub struct Tile {
+    ///The level of the tile. At level 0 the image is one pixel, at the last level it has its
+    ///full size.
+    pub level: u32,
+    ///The column of the tile.
+    pub x: usize,
+    ///The row of the tile.
+    pub y: usize,
+    ///The part of the image the tile shows at the resolution of its level.
+    pub job: RenderJob,
+    ///The paths relative to the directory of the pyramid the tile is written to, see dzi_path
+    ///and xyz_path.
+    pub paths: Vec<PathBuf>,
+}
+
+///Returns the highest level of the pyramid of an image, at which it has its full size. Each
+///level below halves the size, rounded up, down to a single pixel at level 0. This is the
+///numbering of Deep Zoom.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+pub fn max_level(bounds: (usize, usize)) -> u32 {
+    let size = bounds.0.max(bounds.1).max(1);
+    usize::BITS - (size - 1).leading_zeros()
+}
+
+///Returns the dimensions of the image at a level of the pyramid, see max_level.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+/// * `level` - The level of the pyramid.
+pub fn level_bounds(bounds: (usize, usize), level: u32) -> (usize, usize) {
+    let scale = 1 << (max_level(bounds) - level);
+    (
+        bounds.0.div_ceil(scale).max(1),
+        bounds.1.div_ceil(scale).max(1),
+    )
+}
+
+///Returns the highest zoom of the XYZ pyramid of an image, at which it has its full size. At
+///zoom 0 the image fits into one tile. The tiles of zoom z are the ones of the Deep Zoom level
+///z + max_level - max_zoom.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+pub fn max_zoom(bounds: (usize, usize)) -> u32 {
+    max_level(bounds).saturating_sub(max_level((TILE_SIZE, TILE_SIZE)))
+}
+
+///Splits the image of job into the tiles of all levels of the pyramid. Every level shows the
+///whole viewport, so every tile is computed on its own instead of being scaled down from the
+///level above.
+///
+///The Deep Zoom tiles at the right and the bottom edge can be smaller than TILE_SIZE, but XYZ
+///viewers expect every tile to be TILE_SIZE. So a tile of an XYZ zoom is written to both
+///layouts if it is whole, otherwise the XYZ layout gets a tile of its own which continues the
+///image beyond its edge.
+///
+/// # Arguments
+///
+/// * `job` - The image at full size.
+pub fn tiles(job: &RenderJob) -> Vec<Tile> {
+    let mut tiles = vec![];
+    let max_level = max_level(job.bounds);
+    let first_zoom_level = max_level - max_zoom(job.bounds);
+    for level in 0..=max_level {
+        let level_job = RenderJob {
+            bounds: level_bounds(job.bounds, level),
+            ..job.clone()
+        };
+        let zoom = level.checked_sub(first_zoom_level);
+        for y in 0..level_job.bounds.1.div_ceil(TILE_SIZE) {
+            for x in 0..level_job.bounds.0.div_ceil(TILE_SIZE) {
+                let corner = (x * TILE_SIZE, y * TILE_SIZE);
+                let size = (
+                    TILE_SIZE.min(level_job.bounds.0 - corner.0),
+                    TILE_SIZE.min(level_job.bounds.1 - corner.1),
+                );
+                let whole = size == (TILE_SIZE, TILE_SIZE);
+                let mut paths = vec![dzi_path(level, x, y)];
+                if let (Some(zoom), true) = (zoom, whole) {
+                    paths.push(xyz_path(zoom, x, y));
+                }
+                tiles.push(Tile {
+                    level,
+                    x,
+                    y,
+                    job: level_job.region(corner, size),
+                    paths,
+                });
+                if let (Some(zoom), false) = (zoom, whole) {
+                    tiles.push(Tile {
+                        level,
+                        x,
+                        y,
+                        job: level_job.region(corner, (TILE_SIZE, TILE_SIZE)),
+                        paths: vec![xyz_path(zoom, x, y)],
+                    });
+                }
+            }
+        }
+    }
+    tiles
+}
+
+///Returns the Deep Zoom descriptor of the pyramid of an image with png tiles of TILE_SIZE
+///pixels without overlap.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+pub fn dzi(bounds: (usize, usize)) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"png\" Overlap=\"0\" TileSize=\"{}\">\n  \
+         <Size Width=\"{}\" Height=\"{}\"/>\n\
+         </Image>\n",
+        TILE_SIZE, bounds.0, bounds.1
+    )
+}
+
+///Returns the path of a Deep Zoom tile relative to the directory of the descriptor, which is
+///where a Deep Zoom viewer looks for it: `tiles_files/level/x_y.png`.
+///
+/// # Arguments
+///
+/// * `level` - The level of the tile, see max_level.
+/// * `x` - The column of the tile.
+/// * `y` - The row of the tile.
+pub fn dzi_path(level: u32, x: usize, y: usize) -> PathBuf {
+    Path::new(&format!("{}_files", NAME))
+        .join(level.to_string())
+        .join(format!("{}_{}.png", x, y))
+}
+
+///Returns the path of an XYZ tile relative to the directory of the pyramid: `z/x/y.png`.
+///
+/// # Arguments
+///
+/// * `zoom` - The zoom of the tile, see max_zoom.
+/// * `x` - The column of the tile.
+/// * `y` - The row of the tile.
+pub fn xyz_path(zoom: u32, x: usize, y: usize) -> PathBuf {
+    Path::new(&zoom.to_string())
+        .join(x.to_string())
+        .join(format!("{}.png", y))
+}
+
+///Renders the pyramid of the image of job with mechanism and writes every tile to its paths as
+///soon as it is done, see tiles. The Deep Zoom tiles go to `dir/tiles_files/level/x_y.png` next
+///to the descriptor `dir/tiles.dzi`, the XYZ tiles to `dir/z/x/y.png`. Each tile is a unit of
+///Mechanism::compute_units. Returns the time in ms it took to compute and write the tiles.
+///
+/// # Arguments
+///
+/// * `mechanism` - The mechanism which computes the tiles.
+/// * `job` - The image at full size. Its threads are the threads computing the tiles.
+/// * `dir` - The directory of the pyramid.
+/// * `colouring` - Maps the iteration counts to colours. If None, the tiles are written in
+///   grayscale. The histogram mapping would colour every tile differently and is rejected.
+pub fn write_tiles(
+    mechanism: &dyn Mechanism,
+    job: &RenderJob,
+    dir: &str,
+    colouring: Option<&Colouring>,
+) -> Result<f64, CustomError> {
+    if let Some(Mapping::Histogram) = colouring.map(|c| c.mapping) {
+        return Err(CustomError::InvalidArgument);
+    }
+    let tiles = tiles(job);
+    let dir = Path::new(dir);
+    for path in tiles.iter().flat_map(|t| &t.paths) {
+        fs::create_dir_all(dir.join(path).parent().unwrap())?;
+    }
+
+    let mut start = MyTimestamp::new();
+    let mut end = MyTimestamp::new();
+
+    start.gettime(Clock::ClockMonotonicRaw)?;
+    let units: Vec<RenderJob> = tiles.iter().map(|t| t.job.clone()).collect();
+    mechanism.compute_units(&units, job.threads, &|index, pixels| {
+        let tile = &tiles[index];
+        let path = dir.join(&tile.paths[0]);
+        write_image(
+            &path.to_string_lossy(),
+            &pixels,
+            tile.job.bounds,
+            job.kernel.max_iter,
+            colouring,
+            &[],
+        )?;
+        //A tile of both layouts is encoded once.
+        for other in &tile.paths[1..] {
+            fs::copy(&path, dir.join(other))?;
+        }
+        Ok(())
+    })?;
+    end.gettime(Clock::ClockMonotonicRaw)?;
+
+    fs::write(dir.join(format!("{}.dzi", NAME)), dzi(job.bounds))?;
+    Ok(start.compute_time_millis(end))
+}
@@ -3,6 +3,7 @@ use libc::c_int;
 use libc::timespec;
 
 ///The clock_gettime function of c inbound in Rust
+#[allow(unused_doc_comments)]
 #[link(name = "c")]
 extern "C" {
     pub fn clock_gettime(clk_id: c_int, tp: *mut timespec) -> c_int;
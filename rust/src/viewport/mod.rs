@@ -0,0 +1,277 @@
+use crate::customerror::CustomError;
+use num::Complex;
+
+///The height of the viewport at zoom 1, which shows the whole Mandelbrot set.
+pub const UNZOOMED_HEIGHT: f64 = 4.0;
+
+///The rectangle of the complex plane an image shows. It can be turned around its center.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    ///The upper left point on the complex plane designating the area of the image before it is
+    ///turned.
+    pub upper_left: Complex<f64>,
+    ///The lower right point on the complex plane designating the area of the image before it is
+    ///turned.
+    pub lower_right: Complex<f64>,
+    ///The angle in degrees the rectangle is turned counterclockwise around its center.
+    pub rotation: f64,
+}
+
+///Maps the pixels of an image to the complex plane, see Viewport::transform. The point of a pixel
+///is computed in the rectangle first and then turned around its center, which is an affine
+///transform.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    viewport: Viewport,
+    bounds: (usize, usize),
+    ///The center and the rotation as a complex number of length 1, if the viewport is turned.
+    turn: Option<(Complex<f64>, Complex<f64>)>,
+}
+
+impl Transform {
+    ///Returns the point of a pixel in the rectangle before it is turned.
+    fn unturned(&self, pixel: (usize, usize)) -> Complex<f64> {
+        let (upper_left, bounds) = (self.viewport.upper_left, self.bounds);
+        Complex {
+            re: upper_left.re + pixel.0 as f64 * self.viewport.width() / bounds.0 as f64,
+            // Why subtraction here? pixel.1 increases as we go down,
+            // but the imaginary component increases as we go up.
+            im: upper_left.im - pixel.1 as f64 * self.viewport.height() / bounds.1 as f64,
+        }
+    }
+
+    /// Given the row and column of a pixel in the output image, return the
+    /// corresponding point on the complex plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixel` - A (column, row) pair indicating a particular pixel in that image.
+    pub fn pixel_to_point(&self, pixel: (usize, usize)) -> Complex<f64> {
+        let point = self.unturned(pixel);
+        match self.turn {
+            Some((center, turn)) => center + (point - center) * turn,
+            None => point,
+        }
+    }
+
+    ///Returns the points on the complex plane of all pixels of a row.
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - The row of the pixels in the image.
+    pub fn row(&self, row: usize) -> Vec<Complex<f64>> {
+        (0..self.bounds.0)
+            .map(|column| self.pixel_to_point((column, row)))
+            .collect()
+    }
+}
+
+impl Viewport {
+    ///Returns the viewport between two corners.
+    ///
+    /// # Arguments
+    ///
+    /// * `upper_left` - The upper left point on the complex plane designating the area of the image.
+    /// * `lower_right` - The lower right point on the complex plane designating the area of the image.
+    pub fn from_corners(upper_left: Complex<f64>, lower_right: Complex<f64>) -> Self {
+        Viewport {
+            upper_left,
+            lower_right,
+            rotation: 0.0,
+        }
+    }
+
+    ///Returns the viewport of a width and a height around a center.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The point on the complex plane at the center of the image.
+    /// * `width` - The width of the rectangle on the complex plane.
+    /// * `height` - The height of the rectangle on the complex plane.
+    pub fn from_size(center: Complex<f64>, width: f64, height: f64) -> Self {
+        let half = Complex::new(width, -height) / 2.0;
+        Viewport {
+            upper_left: center - half,
+            lower_right: center + half,
+            rotation: 0.0,
+        }
+    }
+
+    ///Returns the viewport around a center with square pixels. At zoom 1 its height is
+    ///UNZOOMED_HEIGHT, a zoom of 2 halves it.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The point on the complex plane at the center of the image.
+    /// * `zoom` - The magnification.
+    /// * `bounds` - The dimensions of the image, which decide the width.
+    pub fn from_center(center: Complex<f64>, zoom: f64, bounds: (usize, usize)) -> Self {
+        let height = UNZOOMED_HEIGHT / zoom;
+        Viewport::from_size(center, height * bounds.0 as f64 / bounds.1 as f64, height)
+    }
+
+    ///Parses a viewport from the upper left and the lower right corner as four comma separated
+    ///numbers. A leading comma is skipped, which older versions required.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The corners, for example -1.6,1.2,0.6,-1.2.
+    pub fn parse_corners(value: &str) -> Result<Self, CustomError> {
+        let split: Vec<&str> = value.trim_start_matches(',').split(',').collect();
+        if split.len() != 4 {
+            return Err(CustomError::InvalidArgument);
+        }
+        Ok(Viewport::from_corners(
+            Complex {
+                re: split[0].parse::<f64>()?,
+                im: split[1].parse::<f64>()?,
+            },
+            Complex {
+                re: split[2].parse::<f64>()?,
+                im: split[3].parse::<f64>()?,
+            },
+        ))
+    }
+
+    ///Returns the point on the complex plane at the center of the image.
+    pub fn center(&self) -> Complex<f64> {
+        (self.upper_left + self.lower_right) / 2.0
+    }
+
+    ///Returns the width of the rectangle on the complex plane.
+    pub fn width(&self) -> f64 {
+        self.lower_right.re - self.upper_left.re
+    }
+
+    ///Returns the height of the rectangle on the complex plane.
+    pub fn height(&self) -> f64 {
+        self.upper_left.im - self.lower_right.im
+    }
+
+    ///Returns the magnification, see from_center.
+    pub fn zoom(&self) -> f64 {
+        UNZOOMED_HEIGHT / self.height()
+    }
+
+    ///Returns the viewport with the same center and the width or the height grown, so that the
+    ///pixels of an image with bounds are square and it still shows the whole rectangle.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - The dimensions of the image.
+    pub fn square(&self, bounds: (usize, usize)) -> Self {
+        let aspect = bounds.0 as f64 / bounds.1 as f64;
+        let (width, height) = (self.width(), self.height());
+        if width == height * aspect {
+            return *self;
+        }
+        Viewport {
+            rotation: self.rotation,
+            ..Viewport::from_size(
+                self.center(),
+                width.max(height * aspect),
+                height.max(width / aspect),
+            )
+        }
+    }
+
+    ///Returns the viewport turned by an angle around its center.
+    ///
+    /// # Arguments
+    ///
+    /// * `degrees` - The angle in degrees, counterclockwise.
+    pub fn rotate(&self, degrees: f64) -> Self {
+        Viewport {
+            rotation: self.rotation + degrees,
+            ..*self
+        }
+    }
+
+    ///Returns the map from the pixels of an image with bounds to the complex plane. It computes
+    ///the rotation once, so it is cheaper than calling pixel_to_point for every pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - A pair giving the width and height of the image in pixels.
+    pub fn transform(&self, bounds: (usize, usize)) -> Transform {
+        let turn = if self.rotation == 0.0 {
+            None
+        } else {
+            let (sin, cos) = self.rotation.to_radians().sin_cos();
+            Some((self.center(), Complex::new(cos, sin)))
+        };
+        Transform {
+            viewport: *self,
+            bounds,
+            turn,
+        }
+    }
+
+    /// Given the row and column of a pixel in the output image, return the
+    /// corresponding point on the complex plane.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - A pair giving the width and height of the image in pixels.
+    /// * `pixel` - A (column, row) pair indicating a particular pixel in that image.
+    pub fn pixel_to_point(&self, bounds: (usize, usize), pixel: (usize, usize)) -> Complex<f64> {
+        self.transform(bounds).pixel_to_point(pixel)
+    }
+
+    ///Returns the viewport of a rectangle of an image. The rectangle is turned by the same angle
+    ///around the center of the image, so its pixels are the same points as in the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - The dimensions of the image.
+    /// * `corner` - The column and row of the upper left pixel of the rectangle.
+    /// * `size` - The width and height of the rectangle in pixels.
+    pub fn region(
+        &self,
+        bounds: (usize, usize),
+        corner: (usize, usize),
+        size: (usize, usize),
+    ) -> Viewport {
+        let transform = self.transform(bounds);
+        let region = Viewport {
+            upper_left: transform.unturned(corner),
+            lower_right: transform.unturned((corner.0 + size.0, corner.1 + size.1)),
+            rotation: self.rotation,
+        };
+        //The region is turned around its own center, so that center is moved to where the
+        //rotation around the center of the image puts it.
+        match transform.turn {
+            Some((center, turn)) => {
+                let shift = center + (region.center() - center) * turn - region.center();
+                Viewport {
+                    upper_left: region.upper_left + shift,
+                    lower_right: region.lower_right + shift,
+                    ..region
+                }
+            }
+            None => region,
+        }
+    }
+
+    ///Returns the options which select the viewport on the command line, --complexcoords and
+    ///--rotation if it is turned.
+    pub fn arguments(&self) -> String {
+        if self.rotation == 0.0 {
+            format!("--complexcoords {}", self.complexcoords())
+        } else {
+            format!(
+                "--complexcoords {} --rotation {}",
+                self.complexcoords(),
+                self.rotation
+            )
+        }
+    }
+
+    ///Returns the viewport in the format of --complexcoords. The rotation is not included.
+    pub fn complexcoords(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.upper_left.re, self.upper_left.im, self.lower_right.re, self.lower_right.im
+        )
+    }
+}
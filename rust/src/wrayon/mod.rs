@@ -1,80 +1,87 @@
 use crate::customerror::CustomError;
-use crate::mandel::{pixel_to_point, render, write_image};
-use crate::time::{Clock, MyTimestamp};
-use num::Complex;
+use crate::mandel::{render, Buffer, Sample};
+use crate::marianisilver::{MarianiSilver, Rect};
+use crate::mechanism::{Mechanism, Partitioning, RenderJob, Strategy, UnitSink};
 use rayon::prelude::*;
-use std::fs::File;
-use std::io::prelude::*;
 
-///Measure in ms how long it takes to compute an image of the mandelbrot set in parallel
-///using the rayon crate.
+///Computes an image of the mandelbrot set in parallel using the rayon crate.
+///The image is split into bands of rows_per_band rows, rayon doesn't care about threads.
+///With the Mariani-Silver strategy the halves of every split rectangle are passed to
+///rayon::join instead.
+pub struct Rayon;
 
-/// # Arguments
-///
-/// * `bounds` - A pair giving the width and height of the image in pixels.
-/// * `upper_left` - The upper left point on the complex plane designating the area of the image.
-/// * `lower_right` - The lower right point on the complex plane designating the area of the image.
-/// * `rows_per_band` - Specifies how big a band will be.
-/// * `draw` - Decides whether to write the computed mandelbrot set to png or not.
-pub fn time_rayon(
-    bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
-    rows_per_band: usize,
-    draw: bool,
-) -> Result<f64, CustomError> {
-    let mut pixels = vec![0; bounds.0 * bounds.1];
-    let band_len = rows_per_band * bounds.0;
+impl Mechanism for Rayon {
+    fn name(&self) -> &'static str {
+        "rayon"
+    }
+
+    fn alias(&self) -> &'static str {
+        "ra"
+    }
 
-    let mut start = MyTimestamp::new();
-    let mut end = MyTimestamp::new();
+    fn label(&self) -> &'static str {
+        "rayon"
+    }
+
+    fn partitioning(&self) -> Partitioning {
+        Partitioning::RowsPerBand
+    }
+
+    fn strategy(&self, job: &RenderJob) -> Strategy {
+        job.strategy
+    }
+
+    fn compute(&self, job: &RenderJob, pixels: Buffer) -> Result<Buffer, CustomError> {
+        match pixels {
+            Buffer::Iterations(pixels) => Ok(Buffer::Iterations(compute(job, pixels)?)),
+            Buffer::Smooth(pixels) => Ok(Buffer::Smooth(compute(job, pixels)?)),
+        }
+    }
+
+    ///Passes the units to the global pool of rayon, threads is ignored.
+    fn compute_units(
+        &self,
+        units: &[RenderJob],
+        _threads: usize,
+        sink: &UnitSink,
+    ) -> Result<(), CustomError> {
+        units
+            .par_iter()
+            .enumerate()
+            .try_for_each(|(index, unit)| sink(index, unit.render()?))
+    }
+}
+
+///Computes the pixels of the image described by job, see Rayon.
+fn compute<S: Sample>(job: &RenderJob, mut pixels: Vec<S>) -> Result<Vec<S>, CustomError> {
+    if job.strategy == Strategy::MarianiSilver {
+        let (subdivision, rect) =
+            MarianiSilver::new(&mut pixels, job.bounds, job.viewport, &job.kernel)?;
+        subdivide(&subdivision, rect);
+        return Ok(pixels);
+    }
+
+    let band_len = job.rows_per_band * job.bounds.0;
 
-    start.gettime(Clock::ClockMonotonicRaw)?;
     pixels
         .par_chunks_mut(band_len)
         .into_par_iter()
         .enumerate()
         .for_each(|(i, band)| {
-            let top = rows_per_band * i;
-            let height = band.len() / bounds.0;
-            let band_bounds = (bounds.0, height);
-            let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-            let band_lower_right =
-                pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
-            render(band, band_bounds, band_upper_left, band_lower_right).unwrap();
+            let band_job = job.band(job.rows_per_band * i, band.len() / job.bounds.0);
+            render(band, band_job.bounds, band_job.viewport, &band_job.kernel).unwrap();
         });
 
-    end.gettime(Clock::ClockMonotonicRaw)?;
-    if draw {
-        write_image("mandel.png", &pixels, bounds)?;
-    }
-    Ok(start.compute_time_millis(end))
+    Ok(pixels)
 }
 
-///Measures how long it takes for every number of rows per band in a
-///range from 1 to 80 by repeating the measurement 20 times each. The results are written to a file.
-
-/// # Arguments
-///
-/// * `bounds` - A pair giving the width and height of the image in pixels.
-/// * `upper_left` - The upper left point on the complex plane designating the area of the image.
-/// * `lower_right` - The lower right point on the complex plane designating the area of the image.
-/// * `draw` - Decides whether to write the computed mandelbrot set to png or not.
-pub fn measure_workload_rayon(
-    bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
-    draw: bool,
-) -> Result<(), CustomError> {
-    let mut file = File::create("rust_rayon_performance.txt")?;
-
-    for rows_per_band in 1..=80 {
-        let mut time: f64 = 0.0;
-        for _ in 0..20 {
-            time += time_rayon(bounds, upper_left, lower_right, rows_per_band, draw)?;
-        }
-        time /= 20.0;
-        file.write_fmt(format_args!("{},{}\n", rows_per_band, time))?;
+///Splits a rectangle and computes both halves with rayon::join, which runs them in parallel if a
+///thread of the pool is idle.
+fn subdivide<S: Sample>(subdivision: &MarianiSilver<S>, rect: Rect) {
+    if let Some((first, second)) = subdivision.split(rect) {
+        rayon::join(
+            || subdivide(subdivision, first),
+            || subdivide(subdivision, second),
+        );
     }
-    Ok(())
 }
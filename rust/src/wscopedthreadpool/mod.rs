@@ -1,93 +1,163 @@
 use crate::customerror::CustomError;
-use crate::mandel::{pixel_to_point, render, write_image};
-use crate::time::{Clock, MyTimestamp};
-use num::Complex;
+use crate::mandel::{render, Buffer, Sample};
+use crate::marianisilver::MarianiSilver;
+use crate::mechanism::{Mechanism, Partitioning, RenderJob, Strategy, UnitSink};
 use scoped_threadpool::Pool;
-use std::fs::File;
-use std::io::prelude::*;
-
-///Measure in ms how long it takes to compute an image of the mandelbrot set in parallel
-///using the scoped_threadpool crate.
-
-/// # Arguments
-///
-/// * `bounds` - A pair giving the width and height of the image in pixels.
-/// * `upper_left` - The upper left point on the complex plane designating the area of the image.
-/// * `lower_right` - The lower right point on the complex plane designating the area of the image.
-/// * `rows_per_band` - Specifies how big a band will be.
-/// * `pool_size` - The number of threads the threadpool will be initialized with.
-/// * `draw` - Decides whether to write the computed mandelbrot set to png or not.
-pub fn time_scoped_threadpool(
-    bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
-    rows_per_band: usize,
-    pool_size: usize,
-    draw: bool,
-) -> Result<f64, CustomError> {
-    let mut pixels = vec![0; bounds.0 * bounds.1];
-
-    let bands: Vec<&mut [u8]> = pixels.chunks_mut(rows_per_band * bounds.0).collect();
-
-    let mut start = MyTimestamp::new();
-    let mut end = MyTimestamp::new();
-
-    start.gettime(Clock::ClockMonotonicRaw)?;
-    let mut pool = Pool::new(pool_size as u32);
+use std::sync::Mutex;
+
+///The number of rectangles per thread the Mariani-Silver strategy splits the image into before
+///they are executed on the pool, so threads which finish early find more work.
+static TASKS_PER_THREAD: usize = 4;
+
+///Computes an image of the mandelbrot set in parallel using the scoped_threadpool crate.
+///The image is split into bands of rows_per_band rows and the pool holds one thread per
+///job thread. The pool is kept between calls and only rebuilt if the number of threads changes,
+///so a sequence of images like a zoom animation doesn't spawn new threads for every image.
+///With the Mariani-Silver strategy the image is split into rectangles instead of bands.
+pub struct ScopedThreadpool {
+    pool: Mutex<Option<Pool>>,
+}
+
+impl Default for ScopedThreadpool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScopedThreadpool {
+    ///Returns a ScopedThreadpool without a pool. The pool is built by the first call.
+    pub fn new() -> Self {
+        ScopedThreadpool {
+            pool: Mutex::new(None),
+        }
+    }
+
+    ///Calls f with the cached pool of threads threads.
+    fn with_pool<R>(
+        &self,
+        threads: usize,
+        f: impl FnOnce(&mut Pool) -> R,
+    ) -> Result<R, CustomError> {
+        let threads = threads.max(1) as u32;
+        let mut pool = self.pool.lock().map_err(|_| CustomError::ThreadPanic)?;
+        match *pool {
+            Some(ref p) if p.thread_count() == threads => {}
+            _ => *pool = Some(Pool::new(threads)),
+        }
+        //The pool was set above.
+        Ok(f(pool.as_mut().unwrap()))
+    }
+}
+
+impl Mechanism for ScopedThreadpool {
+    fn name(&self) -> &'static str {
+        "scoped_threadpool"
+    }
+
+    fn alias(&self) -> &'static str {
+        "st"
+    }
+
+    fn label(&self) -> &'static str {
+        "scoped_threadpool"
+    }
+
+    fn partitioning(&self) -> Partitioning {
+        Partitioning::RowsPerBand
+    }
+
+    fn strategy(&self, job: &RenderJob) -> Strategy {
+        job.strategy
+    }
+
+    fn compute(&self, job: &RenderJob, pixels: Buffer) -> Result<Buffer, CustomError> {
+        self.with_pool(job.threads, |pool| match pixels {
+            Buffer::Iterations(pixels) => Ok(Buffer::Iterations(compute(pool, job, pixels)?)),
+            Buffer::Smooth(pixels) => Ok(Buffer::Smooth(compute(pool, job, pixels)?)),
+        })?
+    }
+
+    ///Executes one task per unit on the pool with threads threads. The first error is returned.
+    fn compute_units(
+        &self,
+        units: &[RenderJob],
+        threads: usize,
+        sink: &UnitSink,
+    ) -> Result<(), CustomError> {
+        let error: Mutex<Option<CustomError>> = Mutex::new(None);
+
+        self.with_pool(threads, |pool| {
+            pool.scoped(|scope| {
+                for (index, unit) in units.iter().enumerate() {
+                    let error = &error;
+                    scope.execute(move || {
+                        if let Err(e) = unit.render().and_then(|pixels| sink(index, pixels)) {
+                            error.lock().unwrap().get_or_insert(e);
+                        }
+                    });
+                }
+            })
+        })?;
+
+        match error.into_inner() {
+            Ok(None) => Ok(()),
+            Ok(Some(e)) => Err(e),
+            Err(_) => Err(CustomError::ThreadPanic),
+        }
+    }
+}
+
+///Computes the pixels of the image described by job on pool, see ScopedThreadpool.
+fn compute<S: Sample>(
+    pool: &mut Pool,
+    job: &RenderJob,
+    mut pixels: Vec<S>,
+) -> Result<Vec<S>, CustomError> {
+    if job.strategy == Strategy::MarianiSilver {
+        return compute_subdivided(pool, job, pixels);
+    }
+
+    let bands: Vec<&mut [S]> = pixels
+        .chunks_mut(job.rows_per_band * job.bounds.0)
+        .collect();
+
     pool.scoped(|scope| {
-        for (i, band) in bands.into_iter().enumerate() {
-            let top = rows_per_band * i;
-            let height = band.len() / bounds.0;
-            let band_bounds = (bounds.0, height);
-            let band_upper_left = pixel_to_point(bounds, (0, top), upper_left, lower_right);
-            let band_lower_right =
-                pixel_to_point(bounds, (bounds.0, top + height), upper_left, lower_right);
+        for (band, (_, band_job)) in bands.into_iter().zip(job.bands(job.rows_per_band)) {
             scope.execute(move || {
-                render(band, band_bounds, band_upper_left, band_lower_right).unwrap();
+                render(band, band_job.bounds, band_job.viewport, &band_job.kernel).unwrap();
             });
         }
     });
 
-    end.gettime(Clock::ClockMonotonicRaw)?;
-    if draw {
-        write_image("mandel.png", &pixels, bounds)?;
-    }
-    Ok(start.compute_time_millis(end))
+    Ok(pixels)
 }
 
-///Measures how long it takes for every number of rows per band in a
-///range from 1 to 80 by repeating the measurement 20 times each. The results are written to a file.
-
-/// # Arguments
-///
-/// * `bounds` - A pair giving the width and height of the image in pixels.
-/// * `upper_left` - The upper left point on the complex plane designating the area of the image.
-/// * `lower_right` - The lower right point on the complex plane designating the area of the image.
-/// * `pool_size` - The number of threads the threadpool will be initialized with.
-/// * `draw` - Decides whether to write the computed mandelbrot set to png or not.
-pub fn measure_workload_scoped_threadpool(
-    bounds: (usize, usize),
-    upper_left: Complex<f64>,
-    lower_right: Complex<f64>,
-    pool_size: usize,
-    draw: bool,
-) -> Result<(), CustomError> {
-    let mut file = File::create("rust_scoped_threadpool_performance.txt")?;
-
-    for rows_per_band in 1..=80 {
-        let mut time: f64 = 0.0;
-        for _ in 0..20 {
-            time += time_scoped_threadpool(
-                bounds,
-                upper_left,
-                lower_right,
-                rows_per_band,
-                pool_size,
-                draw,
-            )?;
-        }
-        time /= 20.0;
-        file.write_fmt(format_args!("{},{}\n", rows_per_band, time))?;
+///Computes the pixels of the image described by job on pool with the Mariani-Silver strategy.
+///The rectangles are split on the calling thread until there are enough to keep the threads of
+///the pool busy, then each one is finished by a task.
+fn compute_subdivided<S: Sample>(
+    pool: &mut Pool,
+    job: &RenderJob,
+    mut pixels: Vec<S>,
+) -> Result<Vec<S>, CustomError> {
+    let (subdivision, rect) =
+        MarianiSilver::new(&mut pixels, job.bounds, job.viewport, &job.kernel)?;
+    let tasks = pool.thread_count() as usize * TASKS_PER_THREAD;
+    let mut rects = vec![rect];
+    while !rects.is_empty() && rects.len() < tasks {
+        rects = rects
+            .into_iter()
+            .filter_map(|rect| subdivision.split(rect))
+            .flat_map(|(first, second)| vec![first, second])
+            .collect();
     }
-    Ok(())
+
+    pool.scoped(|scope| {
+        for rect in rects {
+            let subdivision = &subdivision;
+            scope.execute(move || subdivision.finish(rect));
+        }
+    });
+
+    Ok(pixels)
 }
@@ -0,0 +1,134 @@
+mod common;
+
+use common::assert_close;
+use image::gif::GifDecoder;
+use image::AnimationDecoder;
+use mandelbrot::animation::{frame_writer, render_zoom, Zoom};
+use mandelbrot::mandel::Buffer;
+use mandelbrot::mechanism::{Mechanism, RenderJob};
+use mandelbrot::palette::{Colouring, Gradient};
+use mandelbrot::wscopedthreadpool::ScopedThreadpool;
+use num::Complex;
+use std::fs;
+
+fn job() -> RenderJob {
+    common::job((32, 24), common::viewport())
+}
+
+fn zoom() -> Zoom {
+    Zoom {
+        target: Complex { re: -0.75, im: 0.1 },
+        factor: 100.0,
+        frames: 5,
+        rotation: 0.0,
+    }
+}
+
+#[test]
+fn test_zoom_scale() {
+    let zoom = zoom();
+    assert_eq!(zoom.scale(0), 1.0);
+    assert!((zoom.scale(2) - 10.0).abs() < 1e-12);
+    assert!((zoom.scale(4) - 100.0).abs() < 1e-12);
+    let single = Zoom { frames: 1, ..zoom };
+    assert_eq!(single.scale(0), 1.0);
+}
+
+#[test]
+fn test_zoom_frames() {
+    let (job, zoom) = (job(), zoom());
+    let first = zoom.frame(&job, 0);
+    assert_close(first.viewport.upper_left, job.viewport.upper_left);
+    assert_close(first.viewport.lower_right, job.viewport.lower_right);
+
+    let last = zoom.frame(&job, 4);
+    assert_close(
+        (last.viewport.upper_left + last.viewport.lower_right) / 2.0,
+        zoom.target,
+    );
+    assert_close(
+        last.viewport.lower_right - last.viewport.upper_left,
+        (job.viewport.lower_right - job.viewport.upper_left) / 100.0,
+    );
+    assert_eq!(last.bounds, job.bounds);
+}
+
+#[test]
+fn test_zoom_rotation() {
+    let job = job();
+    let zoom = Zoom {
+        rotation: 90.0,
+        ..zoom()
+    };
+    assert_eq!(zoom.frame(&job, 0).viewport.rotation, 0.0);
+    assert_eq!(zoom.frame(&job, 2).viewport.rotation, 45.0);
+    assert_eq!(zoom.frame(&job, 4).viewport.rotation, 90.0);
+}
+
+#[test]
+fn test_render_zoom_reuses_pool() {
+    let mechanism = ScopedThreadpool::new();
+    let mut job = job();
+    let mut frames = vec![];
+    render_zoom(&mechanism, &job, &zoom(), &mut |index, frame, result| {
+        assert!(result.pixels == mechanism.run(frame).unwrap().pixels);
+        frames.push(index);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(frames, vec![0, 1, 2, 3, 4]);
+
+    //A different number of threads rebuilds the pool.
+    job.threads = 3;
+    assert!(mechanism.run(&job).unwrap().pixels == job.render().unwrap());
+}
+
+#[test]
+fn test_y4m_writer() {
+    let filename = std::env::temp_dir().join("mandelbrot_test.y4m");
+    let filename = filename.to_string_lossy();
+    let mut writer = frame_writer(&filename, (3, 2), 2, None, 25.0).unwrap();
+    for _ in 0..2 {
+        writer
+            .write_frame(&Buffer::Iterations(vec![0, 1, 2, 2, 1, 0]))
+            .unwrap();
+    }
+    writer.finish().unwrap();
+    let content = fs::read(&*filename).unwrap();
+    fs::remove_file(&*filename).unwrap();
+
+    let header = b"YUV4MPEG2 W3 H2 F25:1 Ip A1:1 C420jpeg\n";
+    assert_eq!(&content[..header.len()], &header[..]);
+    //Each frame holds 3x2 luma samples and two 2x1 chroma planes.
+    let frame = &content[header.len()..header.len() + 6 + 10];
+    assert_eq!(&frame[..6], b"FRAME\n");
+    //White, gray and black in limited range, gray has no chroma.
+    assert_eq!(&frame[6..12], &[235, 125, 16, 16, 125, 235]);
+    assert_eq!(&frame[12..], &[128, 128, 128, 128]);
+    assert_eq!(content.len(), header.len() + 2 * 16);
+}
+
+#[test]
+fn test_gif_writer() {
+    let filename = std::env::temp_dir().join("mandelbrot_test.gif");
+    let filename = filename.to_string_lossy();
+    let colouring = Colouring::new(Gradient::builtin("fire").unwrap());
+    let mut writer = frame_writer(&filename, (4, 3), 8, Some(&colouring), 10.0).unwrap();
+    for i in 0..3 {
+        writer
+            .write_frame(&Buffer::Iterations(vec![i; 12]))
+            .unwrap();
+    }
+    writer.finish().unwrap();
+
+    let decoder = GifDecoder::new(fs::File::open(&*filename).unwrap()).unwrap();
+    let frames = decoder.into_frames().collect_frames().unwrap();
+    fs::remove_file(&*filename).unwrap();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+    assert_eq!(frames[2].buffer().dimensions(), (4, 3));
+    let expected = colouring.colour(2.0, 8);
+    assert_eq!(frames[2].buffer().get_pixel(0, 0).0, expected);
+
+    assert!(frame_writer(&filename, (4, 3), 8, None, 0.0).is_err());
+}
@@ -0,0 +1,35 @@
+//Fixtures shared by the integration tests, each of them uses only some.
+#![allow(dead_code)]
+
+use mandelbrot::mandel::Kernel;
+use mandelbrot::mechanism::{RenderJob, Strategy};
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+
+///Returns a job for an image of a viewport, computed by 4 threads in bands of 2 rows with 64
+///iterations and brute force. Tests which need other values override them.
+///
+/// # Arguments
+///
+/// * `bounds` - The dimensions of the image.
+/// * `viewport` - The rectangle on the complex plane corresponding to the image.
+pub fn job(bounds: (usize, usize), viewport: Viewport) -> RenderJob {
+    RenderJob {
+        bounds,
+        viewport,
+        threads: 4,
+        rows_per_band: 2,
+        kernel: Kernel::new(64),
+        strategy: Strategy::BruteForce,
+    }
+}
+
+///Returns the viewport from -2 + 1.5i to 2 - 1.5i, which has square pixels at 4:3.
+pub fn viewport() -> Viewport {
+    Viewport::from_corners(Complex { re: -2.0, im: 1.5 }, Complex { re: 2.0, im: -1.5 })
+}
+
+///Asserts that two points are equal up to rounding.
+pub fn assert_close(a: Complex<f64>, b: Complex<f64>) {
+    assert!((a - b).norm() < 1e-12, "{} != {}", a, b);
+}
@@ -1,22 +1,162 @@
+//The baseline test of MyTimestamp is kept as it was written.
+#![allow(clippy::zero_prefixed_literal, clippy::unnecessary_cast)]
+
 #[cfg(test)]
 use libc::timespec;
-use mandelbrot::mandel::pixel_to_point;
+use mandelbrot::formula::{parse_formula, Orbit};
+use mandelbrot::mandel::{render, to_grayscale, Kernel};
 use mandelbrot::time::MyTimestamp;
+use mandelbrot::viewport::Viewport;
 use num::Complex;
 
 #[test]
 fn test_pixel_to_point() {
     assert_eq!(
-        pixel_to_point(
-            (100, 100),
-            (25, 75),
-            Complex { re: -1.0, im: 1.0 },
-            Complex { re: 1.0, im: -1.0 }
-        ),
+        Viewport::from_corners(Complex { re: -1.0, im: 1.0 }, Complex { re: 1.0, im: -1.0 })
+            .pixel_to_point((100, 100), (25, 75)),
         Complex { re: -0.5, im: -0.5 }
     );
 }
 
+#[test]
+fn test_render_max_iter() {
+    let mut pixels = vec![0; 3];
+    //The origin is a member, 4 escapes immediately.
+    render(
+        &mut pixels,
+        (3, 1),
+        Viewport::from_corners(Complex { re: 0.0, im: 0.0 }, Complex { re: 6.0, im: 0.0 }),
+        &Kernel::new(1000),
+    )
+    .unwrap();
+    assert_eq!(pixels[0], 1000);
+    assert_eq!(pixels[2], 0);
+}
+
+#[test]
+fn test_julia_kernel() {
+    //The Julia set of c = 0 is the unit disk.
+    let kernel = Kernel {
+        julia: Some(Complex { re: 0.0, im: 0.0 }),
+        ..Kernel::new(100)
+    };
+    assert_eq!(kernel.escape_iterations(Complex { re: 0.5, im: 0.5 }), None);
+    assert_eq!(
+        kernel.escape_iterations(Complex { re: 1.5, im: 0.0 }),
+        Some(0)
+    );
+    assert_eq!(kernel.iterations(Complex { re: 0.0, im: -0.9 }), 100);
+}
+
+#[test]
+fn test_smooth_iterations() {
+    let kernel = Kernel {
+        bailout: 256.0,
+        smooth: true,
+        ..Kernel::new(500)
+    };
+    //The smooth count lies between the escape iteration and the next one.
+    for re in &[-1.9, -0.8, 0.3, 0.5, 1.0] {
+        let point = Complex { re: *re, im: 0.3 };
+        let smooth = kernel.smooth_iterations(point) as f64;
+        match kernel.escape_iterations(point) {
+            Some(i) => assert!(smooth >= i as f64 && smooth <= i as f64 + 1.0),
+            None => assert_eq!(smooth, 500.0),
+        }
+    }
+    //Neighbouring points have neighbouring counts instead of whole steps.
+    let a = kernel.smooth_iterations(Complex { re: 0.3, im: 0.5 });
+    let b = kernel.smooth_iterations(Complex {
+        re: 0.3,
+        im: 0.5001,
+    });
+    assert!((a - b).abs() < 0.1);
+}
+
+#[test]
+fn test_formulas() {
+    let mandelbrot = parse_formula("mandelbrot").unwrap();
+    let real = parse_formula("multibrot:2.0").unwrap();
+    let cubic = parse_formula("multibrot:3").unwrap();
+    assert_eq!(real.name(), "multibrot:2.0");
+    assert_eq!(cubic.name(), "multibrot:3");
+    let z = Complex { re: -0.3, im: 0.7 };
+    let c = Complex { re: 0.1, im: -0.2 };
+    assert!((real.step(z, c) - mandelbrot.step(z, c)).norm() < 1e-12);
+    assert!((cubic.step(z, c) - (z * z * z + c)).norm() < 1e-12);
+    //Burning Ship and Tricorn only differ from the Mandelbrot formula in the sign of z.
+    let ship = parse_formula("burningship").unwrap();
+    let tricorn = parse_formula("tricorn").unwrap();
+    let mirrored = Complex { re: 0.3, im: 0.7 };
+    assert_eq!(ship.step(z, c), mandelbrot.step(mirrored, c));
+    assert_eq!(tricorn.step(z, c), mandelbrot.step(z.conj(), c));
+    assert!(parse_formula("multibrot:x").is_err());
+    for exponent in &["1", "0", "-2", "0.5", "1.0", "NaN", "inf"] {
+        assert!(parse_formula(&format!("multibrot:{}", exponent)).is_err());
+    }
+    assert!(parse_formula("multibrot:1.5").is_ok());
+    //Only z² + c is quadratic, however it is written.
+    assert!(mandelbrot.quadratic() && real.quadratic());
+    assert!(parse_formula("multibrot:2").unwrap().quadratic());
+    assert!(!cubic.quadratic() && !ship.quadratic() && !tricorn.quadratic());
+    assert!(parse_formula("unknown").is_err());
+}
+
+#[test]
+fn test_shortcut() {
+    let kernel = Kernel {
+        shortcut: true,
+        ..Kernel::new(100)
+    };
+    //The center of the cardioid, a point of the period-2 bulb and one just outside both.
+    assert!(kernel.takes_shortcut(Complex { re: 0.0, im: 0.0 }));
+    assert!(kernel.takes_shortcut(Complex { re: -1.1, im: 0.1 }));
+    assert!(!kernel.takes_shortcut(Complex { re: 0.26, im: 0.0 }));
+    assert!(!kernel.takes_shortcut(Complex { re: -0.75, im: 0.1 }));
+    assert_eq!(kernel.iterations(Complex { re: -1.1, im: 0.1 }), 100);
+
+    let julia = Kernel {
+        julia: Some(Complex { re: 0.0, im: 0.0 }),
+        ..kernel.clone()
+    };
+    assert!(!julia.takes_shortcut(Complex { re: 0.0, im: 0.0 }));
+    let tricorn = Kernel {
+        formula: parse_formula("tricorn").unwrap(),
+        ..kernel
+    };
+    assert!(!tricorn.takes_shortcut(Complex { re: 0.0, im: 0.0 }));
+}
+
+#[test]
+fn test_periodicity() {
+    let kernel = Kernel {
+        periodicity: true,
+        ..Kernel::new(1000)
+    };
+    //The orbit of -1 is -1, 0, -1, ... The first value is saved, so it comes back to it after
+    //the third iteration.
+    assert_eq!(
+        kernel.orbit(Complex { re: -1.0, im: 0.0 }),
+        Orbit::Member(3)
+    );
+    assert_eq!(
+        Kernel::new(1000).orbit(Complex { re: -1.0, im: 0.0 }),
+        Orbit::Member(1000)
+    );
+    match kernel.orbit(Complex { re: -0.12, im: 0.74 }) {
+        Orbit::Member(i) => assert!(i < 1000),
+        Orbit::Escaped(..) => panic!("-0.12 + 0.74i is a member"),
+    }
+    assert_eq!(kernel.iterations(Complex { re: -0.12, im: 0.74 }), 1000);
+    assert_eq!(kernel.iterations(Complex { re: 1.0, im: 1.0 }), 1);
+}
+
+#[test]
+fn test_to_grayscale() {
+    assert_eq!(to_grayscale(&[0, 1, 255, 256], 256), vec![255, 254, 0, 0]);
+    assert_eq!(to_grayscale(&[0, 2048, 4096], 4096), vec![255, 127, 0]);
+}
+
 #[test]
 fn test_compute_time_millis() {
     let x1 = MyTimestamp {
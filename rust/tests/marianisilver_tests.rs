@@ -0,0 +1,46 @@
+use mandelbrot::mandel::{render, Kernel};
+use mandelbrot::marianisilver::{render_mariani_silver, MarianiSilver, MIN_INTERIOR};
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+
+fn viewport() -> Viewport {
+    Viewport::from_corners(Complex { re: -2.0, im: 1.2 }, Complex { re: 0.6, im: -1.2 })
+}
+
+#[test]
+fn test_mariani_silver_matches_render() {
+    let kernel = Kernel::new(128);
+    for bounds in &[(97, 81), (1, 5), (5, 1), (2, 2), (0, 0)] {
+        let len = bounds.0 * bounds.1;
+        let mut expected = vec![0; len];
+        render(&mut expected, *bounds, viewport(), &kernel).unwrap();
+        let mut pixels = vec![0; len];
+        render_mariani_silver(&mut pixels, *bounds, viewport(), &kernel).unwrap();
+        assert_eq!(pixels, expected, "{:?} differs", bounds);
+    }
+    assert!(render_mariani_silver(&mut [0; 3], (2, 2), viewport(), &kernel).is_err());
+}
+
+#[test]
+fn test_split() {
+    let kernel = Kernel::new(64);
+    let mut pixels = vec![0; 40 * 30];
+    let (subdivision, rect) =
+        MarianiSilver::new(&mut pixels, (40, 30), viewport(), &kernel).unwrap();
+    assert_eq!(rect.interior(), 38 * 28);
+    //The line through the middle of the longer side belongs to both halves.
+    let (first, second) = subdivision.split(rect).unwrap();
+    assert_eq!(first.interior(), 19 * 28);
+    assert_eq!(second.interior(), 18 * 28);
+    subdivision.finish(first);
+    subdivision.finish(second);
+
+    //A border of members only is filled without iterating the interior.
+    let inside =
+        Viewport::from_corners(Complex { re: -0.2, im: 0.2 }, Complex { re: 0.1, im: -0.2 });
+    let mut pixels = vec![0; 100 * 100];
+    let (subdivision, rect) = MarianiSilver::new(&mut pixels, (100, 100), inside, &kernel).unwrap();
+    assert!(rect.interior() > MIN_INTERIOR);
+    assert!(subdivision.split(rect).is_none());
+    assert!(pixels.iter().all(|&value| value == 64));
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use mandelbrot::formula::parse_formula;
+use mandelbrot::mandel::{render, Buffer, Kernel};
+use mandelbrot::mechanism::{CostReport, Registry, RenderJob, Strategy, Validation};
+use mandelbrot::perturbation::{parse_point, Reference};
+use mandelbrot::simd::Lanes;
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+use std::sync::Arc;
+
+//A viewport where every pixel and band corner is exactly representable, so the points of a
+//band don't depend on how the image is split.
+fn small_job() -> RenderJob {
+    let viewport =
+        Viewport::from_corners(Complex { re: -2.0, im: 2.0 }, Complex { re: 2.0, im: -2.0 });
+    RenderJob {
+        rows_per_band: 7,
+        kernel: Kernel::new(256),
+        ..common::job((64, 64), viewport)
+    }
+}
+
+#[test]
+fn test_bands_cover_image() {
+    let mut job = small_job();
+    job.bounds = (64, 45);
+    let bands = job.bands(job.rows_per_thread());
+    assert_eq!(bands.len(), 4);
+    assert_eq!(bands[3].0, 36);
+    assert_eq!(bands[3].1.bounds, (64, 9));
+    assert_eq!(bands.iter().map(|(_, b)| b.bounds.1).sum::<usize>(), 45);
+}
+
+fn assert_mechanisms_match_render(job: &RenderJob) {
+    let len = job.bounds.0 * job.bounds.1;
+    let expected = if job.kernel.smooth {
+        let mut pixels = vec![0.0; len];
+        render(&mut pixels, job.bounds, job.viewport, &job.kernel).unwrap();
+        Buffer::Smooth(pixels)
+    } else {
+        let mut pixels = vec![0; len];
+        render(&mut pixels, job.bounds, job.viewport, &job.kernel).unwrap();
+        Buffer::Iterations(pixels)
+    };
+
+    let registry = Registry::default();
+    for mechanism in registry.select("all").unwrap() {
+        let result = mechanism.run(job).unwrap();
+        assert!(result.pixels == expected, "{} differs", mechanism.name());
+    }
+}
+
+#[test]
+fn test_mechanisms_match_render() {
+    assert_mechanisms_match_render(&small_job());
+}
+
+#[test]
+fn test_mechanisms_match_render_julia() {
+    let mut job = small_job();
+    job.kernel.julia = Some(Complex {
+        re: -0.8,
+        im: 0.156,
+    });
+    assert_mechanisms_match_render(&job);
+}
+
+#[test]
+fn test_mechanisms_match_render_burning_ship() {
+    let mut job = small_job();
+    job.kernel.formula = parse_formula("burningship").unwrap();
+    assert_mechanisms_match_render(&job);
+}
+
+#[test]
+fn test_mechanisms_match_render_smooth() {
+    let mut job = small_job();
+    job.kernel.smooth = true;
+    job.kernel.bailout = 256.0;
+    assert_mechanisms_match_render(&job);
+}
+
+#[test]
+fn test_mechanisms_match_render_shortcut() {
+    let mut job = small_job();
+    job.kernel.shortcut = true;
+    assert_mechanisms_match_render(&job);
+    //The shortcut doesn't change the image.
+    let plain = small_job().render().unwrap();
+    assert!(job.render().unwrap() == plain);
+    assert!(job.shortcut_fraction() > 0.05);
+    assert_eq!(small_job().shortcut_fraction(), 0.0);
+}
+
+#[test]
+fn test_mechanisms_match_render_periodicity() {
+    let mut job = small_job();
+    job.kernel.periodicity = true;
+    assert_mechanisms_match_render(&job);
+    assert!(job.render().unwrap() == small_job().render().unwrap());
+}
+
+#[test]
+fn test_mechanisms_match_render_simd() {
+    let mut job = small_job();
+    job.kernel.simd = Some(Lanes::detect());
+    assert_mechanisms_match_render(&job);
+    assert!(job.render().unwrap() == small_job().render().unwrap());
+    //A rotated viewport and a width which doesn't fill the last group.
+    job.bounds = (61, 64);
+    job.viewport = job.viewport.rotate(20.0);
+    job.kernel.shortcut = true;
+    assert_mechanisms_match_render(&job);
+    job.kernel.simd = None;
+    let expected = job.render().unwrap();
+    for lanes in &[Lanes::Scalar, Lanes::Sse2, Lanes::Avx2, Lanes::Avx512] {
+        job.kernel.simd = Some(*lanes);
+        assert!(
+            job.render().unwrap() == expected,
+            "{} differs",
+            lanes.name()
+        );
+    }
+}
+
+#[test]
+fn test_mechanisms_match_render_reference() {
+    //The pixels are offsets from the center of a deep zoom.
+    let mut job = small_job();
+    job.viewport = Viewport::from_center(Complex { re: 0.0, im: 0.0 }, 1e18, job.bounds);
+    let center =
+        parse_point("-1.7693831791955150182138472860854,0.0042368479187367722149265071713");
+    job.kernel.max_iter = 5000;
+    job.kernel.reference = Some(Arc::new(Reference::new(center.unwrap(), None, 5000, 2.0)));
+    assert_mechanisms_match_render(&job);
+    job.strategy = Strategy::MarianiSilver;
+    assert_mechanisms_match_render(&job);
+}
+
+#[test]
+fn test_mariani_silver() {
+    let mut job = small_job();
+    job.strategy = Strategy::MarianiSilver;
+    assert_mechanisms_match_render(&job);
+    assert!(job.render().unwrap() == small_job().render().unwrap());
+    job.kernel.smooth = true;
+    job.kernel.bailout = 256.0;
+    assert_mechanisms_match_render(&job);
+
+    let registry = Registry::default();
+    for mechanism in registry.select("all").unwrap() {
+        let validation = Validation::run(mechanism, &job).unwrap();
+        assert_eq!(validation.pixels, 64 * 64);
+        assert!(validation.passed(), "{} differs", mechanism.name());
+        //Only rayon and scoped_threadpool implement the strategy, the others are brute force.
+        let expected = match mechanism.name() {
+            "rayon" | "scoped_threadpool" => Strategy::MarianiSilver,
+            _ => Strategy::BruteForce,
+        };
+        assert_eq!(validation.strategy, expected, "{}", mechanism.name());
+    }
+    assert_eq!(Strategy::parse("mariani-silver").unwrap(), job.strategy);
+    assert_eq!(job.brute_force().strategy, Strategy::BruteForce);
+    assert!(Strategy::parse("quadtree").is_err());
+}
+
+#[test]
+fn test_cost_report() {
+    let mut job = small_job();
+    job.kernel.shortcut = true;
+    job.kernel.periodicity = true;
+    let report = CostReport::new(&job);
+    //Every member costs the whole iteration limit with the plain kernel.
+    assert_eq!(report.plain, job.plain().iteration_cost());
+    assert_eq!(
+        report.plain.interior,
+        report.plain.members as u64 * job.kernel.max_iter as u64
+    );
+    assert_eq!(report.cost.members, report.plain.members);
+    assert_eq!(report.cost.exterior, report.plain.exterior);
+    assert!(report.cost.interior < report.plain.interior / 5);
+    assert_eq!(report.interior_share(), 1.0);
+
+    let plain = CostReport::new(&small_job());
+    assert_eq!(plain.plain, plain.cost);
+    assert_eq!(plain.interior_share(), 0.0);
+}
+
+#[test]
+fn test_stream_matches_run() {
+    let job = small_job();
+    let registry = Registry::default();
+    for mechanism in registry.select("all").unwrap() {
+        let expected = mechanism.run(&job).unwrap().pixels;
+        let mut values = vec![];
+        let mut slabs = 0;
+        mechanism
+            .stream(&job, &mut |slab| {
+                values.extend((0..slab.len()).map(|i| slab.value(i) as u32));
+                slabs += 1;
+                Ok(())
+            })
+            .unwrap();
+        //64 rows in slabs of threads * rows_per_band = 28 rows.
+        assert_eq!(slabs, 3);
+        assert!(
+            Buffer::Iterations(values) == expected,
+            "{} differs",
+            mechanism.name()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use mandelbrot::mandel::{
+    read_text, write_exr, write_npy, write_pgm, write_png16, write_ppm, write_raw, Buffer, Kernel,
+};
+use mandelbrot::mechanism::{Mechanism, RenderJob, RenderResult};
+use mandelbrot::output::{expand_frame, metadata, per_frame, ImageOutput};
+use mandelbrot::palette::{Colouring, Gradient, Mapping};
+use mandelbrot::viewport::Viewport;
+use mandelbrot::wcrossbeam::Crossbeam;
+use num::Complex;
+use std::convert::TryInto;
+use std::fs;
+
+fn job() -> RenderJob {
+    let viewport =
+        Viewport::from_corners(Complex { re: -1.6, im: 1.2 }, Complex { re: 0.6, im: -1.2 });
+    RenderJob {
+        threads: 8,
+        kernel: Kernel::new(512),
+        ..common::job((640, 480), viewport)
+    }
+}
+
+#[test]
+fn test_output_path() {
+    let output =
+        ImageOutput::new("out/{mechanism}_{width}x{height}_{threads}_{rows}_{max_iter}.bmp");
+    assert_eq!(
+        output.path(&Crossbeam, &job()),
+        "out/crossbeam_640x480_8_2_512.bmp"
+    );
+}
+
+#[test]
+fn test_output_per_mechanism() {
+    let mut output = ImageOutput::new("out/mandel.png");
+    output.per_mechanism();
+    assert_eq!(output.path(&Crossbeam, &job()), "out/mandel_crossbeam.png");
+
+    let mut output = ImageOutput::new("{mechanism}/mandel.png");
+    output.per_mechanism();
+    assert_eq!(output.template, "{mechanism}/mandel.png");
+}
+
+#[test]
+fn test_output_per_frame() {
+    assert_eq!(per_frame("out/zoom.png"), "out/zoom_{frame}.png");
+    assert_eq!(per_frame("f{frame}.png"), "f{frame}.png");
+    assert_eq!(
+        expand_frame("out/zoom_{frame}.png", 42),
+        "out/zoom_00042.png"
+    );
+}
+
+fn temp_file(name: &str) -> String {
+    std::env::temp_dir()
+        .join(name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn test_write_pgm_ppm() {
+    let pixels = Buffer::Iterations(vec![0, 128, 256]);
+    let filename = temp_file("mandelbrot_test.pgm");
+
+    write_pgm(&filename, &pixels, (3, 1), 256, false).unwrap();
+    assert_eq!(
+        fs::read(&filename).unwrap(),
+        b"P5\n3 1\n255\n\xff\x7f\x00".to_vec()
+    );
+
+    write_pgm(&filename, &pixels, (3, 1), 256, true).unwrap();
+    assert_eq!(
+        fs::read(&filename).unwrap(),
+        b"P5\n3 1\n65535\n\xff\xff\x7f\xff\x00\x00".to_vec()
+    );
+    fs::remove_file(&filename).unwrap();
+
+    let filename = temp_file("mandelbrot_test.ppm");
+    write_ppm(&filename, &pixels, (3, 1), 256, None).unwrap();
+    assert_eq!(
+        fs::read(&filename).unwrap(),
+        b"P6\n3 1\n255\n\xff\xff\xff\x7f\x7f\x7f\x00\x00\x00".to_vec()
+    );
+    fs::remove_file(&filename).unwrap();
+}
+
+#[test]
+fn test_write_raw() {
+    let job = job();
+    let pixels = Buffer::Smooth(vec![1.5, 512.0]);
+    let filename = temp_file("mandelbrot_test.raw");
+    write_raw(&filename, &pixels, (2, 1), job.viewport, &job.kernel).unwrap();
+    let mut expected = 1.5f32.to_le_bytes().to_vec();
+    expected.extend_from_slice(&512.0f32.to_le_bytes());
+    assert_eq!(fs::read(&filename).unwrap(), expected);
+
+    let sidecar = fs::read_to_string(format!("{}.json", filename)).unwrap();
+    assert!(sidecar.contains("\"width\": 2,"));
+    assert!(sidecar.contains("\"dtype\": \"float32\","));
+    assert!(sidecar.contains("\"upper_left\": [-1.6, 1.2],"));
+    assert!(sidecar.contains("\"julia\": null,"));
+    fs::remove_file(&filename).unwrap();
+    fs::remove_file(format!("{}.json", filename)).unwrap();
+}
+
+#[test]
+fn test_write_npy() {
+    let pixels = Buffer::Iterations(vec![1, 2, 3, 4, 5, 6]);
+    let filename = temp_file("mandelbrot_test.npy");
+    write_npy(&filename, &pixels, (3, 2)).unwrap();
+    let content = fs::read(&filename).unwrap();
+    fs::remove_file(&filename).unwrap();
+
+    assert_eq!(&content[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([content[8], content[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0);
+    let header = std::str::from_utf8(&content[10..10 + header_len]).unwrap();
+    assert!(header.starts_with("{'descr': '<u4', 'fortran_order': False, 'shape': (2, 3), }"));
+    assert!(header.ends_with(" \n"));
+    assert_eq!(content[10 + header_len..], pixels.to_le_bytes()[..]);
+
+    assert!(write_npy(&filename, &pixels, (3, 3)).is_err());
+}
+
+#[test]
+fn test_write_png16() {
+    let pixels = Buffer::Iterations(vec![0, 128, 256, 64]);
+    let filename = temp_file("mandelbrot_test16.png");
+    write_png16(&filename, &pixels, (2, 2), 256, &[]).unwrap();
+    let image = image::open(&filename).unwrap().into_luma16();
+    fs::remove_file(&filename).unwrap();
+
+    assert_eq!(image.dimensions(), (2, 2));
+    assert_eq!(image.into_raw(), pixels.to_grayscale16(256));
+}
+
+#[test]
+fn test_write_exr() {
+    let pixels = Buffer::Smooth(vec![0.5, 1.5, 256.0, 3.25]);
+    let filename = temp_file("mandelbrot_test.exr");
+    write_exr(&filename, &pixels, (2, 2)).unwrap();
+    let content = fs::read(&filename).unwrap();
+    fs::remove_file(&filename).unwrap();
+
+    assert_eq!(&content[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+    //The last scanline block holds y = 1, 8 bytes of data and the two samples.
+    let mut last = vec![];
+    last.extend_from_slice(&1i32.to_le_bytes());
+    last.extend_from_slice(&8i32.to_le_bytes());
+    last.extend_from_slice(&256.0f32.to_le_bytes());
+    last.extend_from_slice(&3.25f32.to_le_bytes());
+    assert_eq!(content[content.len() - 16..], last[..]);
+    //The offset of the last block is the second entry of the offset table.
+    let table = content.len() - 2 * 16 - 16;
+    let offset = u64::from_le_bytes(content[table + 8..table + 16].try_into().unwrap());
+    assert_eq!(offset as usize, content.len() - 16);
+
+    assert!(write_exr(&filename, &pixels, (3, 3)).is_err());
+}
+
+#[test]
+fn test_png_metadata() {
+    let mut job = job();
+    job.bounds = (8, 6);
+    job.kernel.julia = Some(Complex {
+        re: -0.8,
+        im: 0.156,
+    });
+    let result = RenderResult {
+        pixels: Buffer::Iterations(vec![3; 48]),
+        time: 12.5,
+    };
+    let mut output = ImageOutput::new(&temp_file("mandelbrot_test_{mechanism}.png"));
+    let mut colouring = Colouring::new(Gradient::builtin("fire").unwrap());
+    colouring.cycle = Some(64.0);
+    output.colouring = Some(colouring);
+    output.write(&Crossbeam, &job, &result).unwrap();
+    let filename = output.path(&Crossbeam, &job);
+    let text = read_text(&filename).unwrap();
+    fs::remove_file(&filename).unwrap();
+
+    let mut expected = metadata(&Crossbeam, &job, output.colouring.as_ref());
+    expected.push(("time".to_string(), "12.5".to_string()));
+    assert_eq!(text, expected);
+    let value = |key: &str| text.iter().find(|(k, _)| k == key).unwrap().1.as_str();
+    assert_eq!(value("mechanism"), "crossbeam");
+    assert_eq!(value("bounds"), "8,6");
+    assert_eq!(value("complexcoords"), "-1.6,1.2,0.6,-1.2");
+    assert_eq!(value("julia"), "-0.8,0.156");
+    assert_eq!(value("cycle"), "64");
+    assert_eq!(value("time"), "12.5");
+    assert_eq!(
+        Gradient::parse(value("palette")).unwrap(),
+        Gradient::builtin("fire").unwrap()
+    );
+}
+
+#[test]
+fn test_read_text_itxt() {
+    let pixels = Buffer::Iterations(vec![0, 128, 256, 64]);
+    let filename = temp_file("mandelbrot_test_itxt.png");
+    let text = vec![
+        ("ascii".to_string(), "plain".to_string()),
+        ("utf8".to_string(), "z² + c".to_string()),
+    ];
+    write_png16(&filename, &pixels, (2, 2), 256, &text).unwrap();
+    assert_eq!(read_text(&filename).unwrap(), text);
+    fs::remove_file(&filename).unwrap();
+}
+
+#[test]
+fn test_stream_png() {
+    let mut job = job();
+    job.bounds = (40, 30);
+    job.rows_per_band = 1;
+    let output = ImageOutput::new(&temp_file("mandelbrot_test_stream_{mechanism}.png"));
+    output.stream(&Crossbeam, &job).unwrap();
+    let filename = output.path(&Crossbeam, &job);
+    let image = image::open(&filename).unwrap().into_luma8();
+    let text = read_text(&filename).unwrap();
+    fs::remove_file(&filename).unwrap();
+
+    let mut expected = vec![];
+    Crossbeam
+        .stream(&job, &mut |slab| {
+            expected.extend(slab.to_grayscale(job.kernel.max_iter));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(image.dimensions(), (40, 30));
+    assert_eq!(image.into_raw(), expected);
+    assert_eq!(text[1], ("mechanism".to_string(), "crossbeam".to_string()));
+    assert_eq!(text.last().unwrap().0, "time");
+
+    let mut output = output;
+    output.colouring = Some(Colouring::new(Gradient::builtin("gray").unwrap()));
+    output.colouring.as_mut().unwrap().mapping = Mapping::Histogram;
+    assert!(output.stream(&Crossbeam, &job).is_err());
+}
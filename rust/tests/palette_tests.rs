@@ -0,0 +1,65 @@
+use mandelbrot::mandel::Buffer;
+use mandelbrot::palette::{parse_colour, Colouring, Gradient, Histogram, Mapping};
+use std::fs;
+
+#[test]
+fn test_gradient_sample() {
+    let gradient = Gradient::new(vec![(1.0, [200, 100, 0, 255]), (0.0, [0, 0, 0, 255])]).unwrap();
+    assert_eq!(gradient.sample(0.0), [0, 0, 0, 255]);
+    assert_eq!(gradient.sample(0.5), [100, 50, 0, 255]);
+    assert_eq!(gradient.sample(1.0), [200, 100, 0, 255]);
+    assert_eq!(gradient.sample(f64::NAN), [200, 100, 0, 255]);
+    assert!(Gradient::new(vec![(1.5, [0, 0, 0, 255])]).is_err());
+    assert!(Gradient::new(vec![]).is_err());
+}
+
+#[test]
+fn test_colouring() {
+    let gradient = Gradient::new(vec![(0.0, [0, 0, 0, 255]), (1.0, [100, 100, 100, 255])]).unwrap();
+    let mut colouring = Colouring::new(gradient);
+    assert_eq!(colouring.colour(50.0, 100), [50, 50, 50, 255]);
+    assert_eq!(colouring.colour(100.0, 100), [0, 0, 0, 255]);
+    colouring.cycle = Some(20.0);
+    colouring.offset = 0.5;
+    assert_eq!(colouring.colour(25.0, 100), [75, 75, 75, 255]);
+    assert!(!colouring.has_alpha());
+    colouring.interior = parse_colour("0,0,0,0").unwrap();
+    assert!(colouring.has_alpha());
+    assert_eq!(
+        colouring.apply(&Buffer::Iterations(vec![100, 5]), 100),
+        vec![0, 0, 0, 0, 75, 75, 75, 255]
+    );
+}
+
+#[test]
+fn test_gradient_from_file() {
+    let filename = std::env::temp_dir().join("mandelbrot_test_gradient.txt");
+    fs::write(&filename, "# red to blue\n0.0,255,0,0\n\n1.0,0,0,255,128\n").unwrap();
+    let gradient = Gradient::from_file(filename.to_str().unwrap()).unwrap();
+    fs::remove_file(&filename).unwrap();
+    assert_eq!(
+        gradient,
+        Gradient::new(vec![(0.0, [255, 0, 0, 255]), (1.0, [0, 0, 255, 128])]).unwrap()
+    );
+    assert!(parse_colour("1,2").is_err());
+    assert!(parse_colour("1,2,300").is_err());
+}
+
+#[test]
+fn test_histogram() {
+    let pixels = Buffer::Iterations(vec![0, 0, 1, 3, 10, 10]);
+    let histogram = Histogram::new(&pixels, 10);
+    assert_eq!(histogram.position(0.0), 0.0);
+    assert_eq!(histogram.position(1.0), 0.5);
+    assert_eq!(histogram.position(3.0), 0.75);
+    assert_eq!(histogram.position(2.5), 0.75);
+    assert_eq!(histogram.position(0.5), 0.25);
+
+    let gradient = Gradient::new(vec![(0.0, [0, 0, 0, 255]), (1.0, [100, 100, 100, 255])]).unwrap();
+    let mut colouring = Colouring::new(gradient);
+    colouring.mapping = Mapping::Histogram;
+    assert_eq!(
+        colouring.apply(&pixels, 10),
+        vec![0, 0, 0, 0, 0, 0, 50, 50, 50, 75, 75, 75, 0, 0, 0, 0, 0, 0]
+    );
+}
@@ -0,0 +1,184 @@
+use mandelbrot::mandel::{render, Kernel};
+use mandelbrot::perturbation::{parse_point, DoubleDouble, Reference};
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+static BOUNDS: (usize, usize) = (64, 48);
+
+//A center in the seahorse valley with more digits than f64 holds.
+static DEEP: &str = "-0.7436438870371587047521915061147,0.131825904205311970493132056385139";
+
+fn render_deep(center: (DoubleDouble, DoubleDouble), zoom: f64, max_iter: u32) -> Vec<u32> {
+    let kernel = Kernel {
+        reference: Some(Arc::new(Reference::new(center, None, max_iter, 2.0))),
+        ..Kernel::new(max_iter)
+    };
+    let mut pixels = vec![0; BOUNDS.0 * BOUNDS.1];
+    let viewport = Viewport::from_center(Complex { re: 0.0, im: 0.0 }, zoom, BOUNDS);
+    render(&mut pixels, BOUNDS, viewport, &kernel).unwrap();
+    pixels
+}
+
+fn render_plain(center: Complex<f64>, zoom: f64, max_iter: u32) -> Vec<u32> {
+    let mut pixels = vec![0; BOUNDS.0 * BOUNDS.1];
+    let viewport = Viewport::from_center(center, zoom, BOUNDS);
+    render(&mut pixels, BOUNDS, viewport, &Kernel::new(max_iter)).unwrap();
+    pixels
+}
+
+#[test]
+fn test_parse_double_double() {
+    let third = DoubleDouble::parse("0.33333333333333333333333333333333").unwrap();
+    assert_eq!(third.hi, 1.0 / 3.0);
+    assert!(third.lo != 0.0);
+    assert!(
+        (third - DoubleDouble::new(1.0) / DoubleDouble::new(3.0))
+            .hi
+            .abs()
+            < 1e-32
+    );
+    assert_eq!(DoubleDouble::parse("-1.5e-20").unwrap().hi, -1.5e-20);
+    assert_eq!(DoubleDouble::parse("+2").unwrap(), DoubleDouble::new(2.0));
+    assert!(DoubleDouble::parse("1.2.3").is_err());
+    assert!(DoubleDouble::parse("inf").is_err());
+    assert!(parse_point("1,2,3").is_err());
+    //The exponent is limited to the range of f64.
+    assert_eq!(DoubleDouble::parse("1e300").unwrap().hi, 1e300);
+    assert_eq!(DoubleDouble::parse("1e-300").unwrap().hi, 1e-300);
+    for value in &["1e2000000000", "1e-2147483648", "1e331", "1e-331", "1e400"] {
+        assert!(DoubleDouble::parse(value).is_err(), "{}", value);
+    }
+}
+
+#[test]
+fn test_display_double_double() {
+    assert_eq!(DoubleDouble::new(-1.5).to_string(), "-1.5e0");
+    assert_eq!(DoubleDouble::new(0.0).to_string(), "0");
+    //Written and parsed again, the digits beyond f64 survive.
+    let (re, im) = parse_point(DEEP).unwrap();
+    for value in &[re, im] {
+        let again = DoubleDouble::parse(&value.to_string()).unwrap();
+        assert!((again - *value).hi.abs() < 1e-31);
+    }
+    assert_eq!(re.to_string(), "-7.436438870371587047521915061147e-1");
+}
+
+#[test]
+fn test_arithmetic_double_double() {
+    //1 + 1e-20 is 1 in f64, but not as DoubleDouble.
+    let tiny = DoubleDouble::parse("1e-20").unwrap();
+    let sum = DoubleDouble::new(1.0) + tiny;
+    assert_eq!(sum.hi, 1.0);
+    assert!(((sum - DoubleDouble::new(1.0)) - tiny).hi.abs() < 1e-35);
+    let product = sum * sum;
+    assert!(
+        ((product - DoubleDouble::new(1.0)) - tiny * DoubleDouble::new(2.0))
+            .hi
+            .abs()
+            < 1e-35
+    );
+}
+
+#[test]
+fn test_reference_matches_kernel() {
+    //At a zoom f64 can handle, perturbation computes almost the same image. A few chaotic pixels
+    //end up with different counts, because the rounding differs.
+    let center = parse_point("-0.75,0.1").unwrap();
+    assert!(
+        render_deep(center, 1e3, 500) == render_plain(Complex { re: -0.75, im: 0.1 }, 1e3, 500)
+    );
+
+    let center = parse_point(DEEP).unwrap();
+    let deep = render_deep(center, 1e6, 2000);
+    let plain = render_plain(
+        Complex {
+            re: center.0.hi,
+            im: center.1.hi,
+        },
+        1e6,
+        2000,
+    );
+    let differing = deep.iter().zip(&plain).filter(|(a, b)| a != b).count();
+    assert!(differing < deep.len() / 50);
+}
+
+#[test]
+fn test_reference_smooth() {
+    //The smooth count only needs the escaped value, which perturbation returns as well.
+    let kernel = Kernel {
+        bailout: 256.0,
+        smooth: true,
+        ..Kernel::new(500)
+    };
+    let deep = Kernel {
+        reference: Some(Arc::new(Reference::new(
+            parse_point("-0.75,0.1").unwrap(),
+            None,
+            500,
+            256.0,
+        ))),
+        ..kernel.clone()
+    };
+    let viewport = Viewport::from_center(Complex { re: 0.0, im: 0.0 }, 1e3, BOUNDS);
+    let transform = viewport.transform(BOUNDS);
+    for y in 0..BOUNDS.1 {
+        for x in 0..BOUNDS.0 {
+            let offset = transform.pixel_to_point((x, y));
+            let plain = kernel.smooth_iterations(offset + Complex { re: -0.75, im: 0.1 });
+            assert!((deep.smooth_iterations(offset) - plain).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn test_reference_escape() {
+    let zero = DoubleDouble::default();
+    let reference = Reference::new((DoubleDouble::new(-1.0), zero), None, 100, 2.0);
+    assert_eq!(reference.iterations(), 100);
+    let kernel = Kernel::new(100);
+    for point in &[Complex { re: 0.3, im: 0.0 }, Complex { re: -0.8, im: 0.6 }] {
+        let (escape, _) = reference.escape(*point, 100, 2.0);
+        let expected = kernel.escape(point + Complex { re: -1.0, im: 0.0 });
+        assert_eq!(escape.map(|(i, _)| i), expected.map(|(i, _)| i));
+    }
+    //An orbit which escapes before the one of the reference is iterated past its end.
+    let reference = Reference::new((DoubleDouble::new(0.3), zero), None, 100, 2.0);
+    assert!(reference.iterations() < 100);
+    let (escape, rebases) = reference.escape(Complex { re: -0.3, im: 0.0 }, 100, 2.0);
+    assert_eq!(escape, None);
+    assert!(rebases > 0);
+}
+
+#[test]
+fn test_deep_zoom() {
+    //At a zoom of 1e20 f64 can't tell the pixels apart.
+    let center = parse_point(DEEP).unwrap();
+    let plain = render_plain(
+        Complex {
+            re: center.0.hi,
+            im: center.1.hi,
+        },
+        1e20,
+        20000,
+    );
+    assert_eq!(plain.iter().collect::<HashSet<_>>().len(), 1);
+    let deep = render_deep(center, 1e20, 20000);
+    assert!(deep.iter().collect::<HashSet<_>>().len() > 100);
+
+    //A reference eight pixels to the right computes the same points for the pixels which are
+    //eight columns further left.
+    let shift = 8.0 * 4.0 / 1e20 / BOUNDS.1 as f64;
+    let moved = render_deep((center.0 + DoubleDouble::new(shift), center.1), 1e20, 20000);
+    let (mut same, mut total) = (0, 0);
+    for row in 0..BOUNDS.1 {
+        for x in 0..BOUNDS.0 - 8 {
+            total += 1;
+            if moved[row * BOUNDS.0 + x] == deep[row * BOUNDS.0 + x + 8] {
+                same += 1;
+            }
+        }
+    }
+    assert!(same > total * 9 / 10);
+}
@@ -0,0 +1,77 @@
+use mandelbrot::mandel::Kernel;
+use mandelbrot::simd::{iterate, Lanes};
+use num::Complex;
+
+//All lanes, the ones the processor doesn't support fall back to the scalar ones.
+static LANES: [Lanes; 4] = [Lanes::Scalar, Lanes::Sse2, Lanes::Avx2, Lanes::Avx512];
+
+//A row of 13 points, so the last group of every width is only partly filled.
+fn points() -> Vec<Complex<f64>> {
+    (0..13)
+        .map(|i| Complex {
+            re: -2.1 + i as f64 * 0.23,
+            im: 0.1 + i as f64 * 0.05,
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_lanes() {
+    assert_eq!(Lanes::parse("scalar").unwrap(), Lanes::Scalar);
+    assert_eq!(Lanes::parse("auto").unwrap(), Lanes::detect());
+    assert!(Lanes::parse("neon").is_err());
+    for lanes in LANES.iter() {
+        assert_eq!(Lanes::parse(lanes.name()).is_ok(), lanes.supported());
+    }
+}
+
+#[test]
+fn test_iterate_matches_kernel() {
+    let points = points();
+    for julia in &[
+        None,
+        Some(Complex {
+            re: -0.8,
+            im: 0.156,
+        }),
+    ] {
+        let kernel = Kernel {
+            julia: *julia,
+            bailout: 3.0,
+            ..Kernel::new(500)
+        };
+        let expected: Vec<u32> = points.iter().map(|&p| kernel.iterations(p)).collect();
+        for lanes in LANES.iter() {
+            let mut counts = vec![0; points.len()];
+            iterate(*lanes, &points, *julia, 500, 3.0, &mut counts);
+            assert_eq!(counts, expected, "{} differs", lanes.name());
+        }
+    }
+}
+
+#[test]
+fn test_row_iterations() {
+    let points = points();
+    let kernel = Kernel {
+        shortcut: true,
+        simd: Some(Lanes::detect()),
+        ..Kernel::new(300)
+    };
+    assert_eq!(kernel.lanes(), Some(Lanes::detect()));
+    let mut counts = vec![0; points.len()];
+    kernel.row_iterations(&points, &mut counts);
+    let expected: Vec<u32> = points.iter().map(|&p| kernel.iterations(p)).collect();
+    assert_eq!(counts, expected);
+
+    //Smooth counts and periodicity are computed one by one.
+    let smooth = Kernel {
+        smooth: true,
+        ..kernel.clone()
+    };
+    assert_eq!(smooth.lanes(), None);
+    let periodicity = Kernel {
+        periodicity: true,
+        ..kernel
+    };
+    assert_eq!(periodicity.lanes(), None);
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::assert_close;
+use mandelbrot::mandel::Buffer;
+use mandelbrot::terminal::{fit, parse_keys, preview, to_ansi, Action, Explorer, Key};
+use mandelbrot::wrayon::Rayon;
+use num::Complex;
+
+#[test]
+fn test_fit() {
+    //A square image needs twice as many columns as rows.
+    assert_eq!(fit((100, 100), (80, 24)), (48, 24));
+    assert_eq!(fit((400, 100), (80, 24)), (80, 10));
+    assert_eq!(fit((1, 1), (0, 0)), (1, 1));
+}
+
+#[test]
+fn test_to_ansi() {
+    //Escaping at once on the left, interior on the right, with a gray column in between.
+    let pixels = Buffer::Iterations(vec![
+        0, 0, 5, 10, 10, //
+        0, 0, 5, 10, 10, //
+        0, 0, 5, 10, 10, //
+        0, 0, 5, 10, 10,
+    ]);
+    let ansi = to_ansi(&pixels, (5, 4), (2, 1), 10, None).unwrap();
+    let lines: Vec<&str> = ansi.lines().collect();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].ends_with("\x1b[0m"));
+    assert_eq!(lines[0].matches('\u{2580}').count(), 2);
+    //The grayscale maps 0 iterations to white and max_iter to black.
+    assert!(lines[0].starts_with("\x1b[38;2;255;255;255m\x1b[48;2;255;255;255m\u{2580}"));
+    let right = "\x1b[38;2;42;42;42m\x1b[48;2;42;42;42m\u{2580}";
+    assert!(lines[0].contains(right), "{:?}", lines[0]);
+
+    assert!(to_ansi(&pixels, (4, 4), (2, 1), 10, None).is_err());
+}
+
+#[test]
+fn test_preview() {
+    let job = common::job((4000, 3000), common::viewport());
+    let (ansi, result) = preview(&Rayon, &job, (40, 20), None).unwrap();
+    assert_eq!(ansi.lines().count(), 15);
+    //Every half-block is rendered from 2x2 pixels.
+    assert_eq!(result.pixels.len(), 80 * 60);
+}
+
+#[test]
+fn test_parse_keys() {
+    assert_eq!(
+        parse_keys(b"\x1b[A\x1b[D+q\x1b[5~c"),
+        vec![
+            Key::Up,
+            Key::Left,
+            Key::Char('+'),
+            Key::Char('q'),
+            Key::Char('~'),
+            Key::Char('c')
+        ]
+    );
+}
+
+#[test]
+fn test_explorer() {
+    let job = common::job((40, 30), common::viewport());
+    let mut explorer = Explorer::new(job, None);
+    assert_eq!(explorer.handle(Key::Right).unwrap(), Action::Render);
+    assert_eq!(explorer.handle(Key::Up).unwrap(), Action::Render);
+    assert_close(
+        explorer.job.viewport.upper_left,
+        Complex { re: -1.6, im: 1.8 },
+    );
+    assert_eq!(explorer.handle(Key::Char('+')).unwrap(), Action::Render);
+    assert_close(
+        explorer.job.viewport.upper_left,
+        Complex { re: -0.6, im: 1.05 },
+    );
+    assert_close(
+        explorer.job.viewport.lower_right,
+        Complex { re: 1.4, im: -0.45 },
+    );
+    explorer.handle(Key::Char('-')).unwrap();
+    assert_close(
+        explorer.job.viewport.lower_right,
+        Complex { re: 2.4, im: -1.2 },
+    );
+
+    explorer.handle(Key::Char('3')).unwrap();
+    assert_eq!(explorer.job.kernel.formula.name(), "tricorn");
+    assert!(explorer.colouring.is_none());
+    explorer.handle(Key::Char('7')).unwrap();
+    assert!(explorer.colouring.is_some());
+    assert_eq!(explorer.palette, Some("fire"));
+
+    assert_eq!(
+        explorer.handle(Key::Char('c')).unwrap(),
+        Action::Show(format!(
+            "--complexcoords {}",
+            explorer.job.viewport.complexcoords()
+        ))
+    );
+    assert_eq!(explorer.handle(Key::Char('x')).unwrap(), Action::Nothing);
+    assert_eq!(explorer.handle(Key::Char('q')).unwrap(), Action::Quit);
+    assert_eq!(
+        explorer.status("rayon", 1.24, None, 22),
+        "rayon 1.2ms | tricorn "
+    );
+}
+
+#[test]
+fn test_explorer_rotation() {
+    let job = common::job((40, 30), common::viewport().rotate(90.0));
+    let mut explorer = Explorer::new(job, None);
+    //Turned by 90 degrees, right on the screen is up on the complex plane.
+    explorer.handle(Key::Right).unwrap();
+    assert_close(explorer.job.viewport.center(), Complex { re: 0.0, im: 0.4 });
+    explorer.handle(Key::Char('+')).unwrap();
+    assert_eq!(explorer.job.viewport.rotation, 90.0);
+    assert_close(explorer.job.viewport.center(), Complex { re: 0.0, im: 0.4 });
+    assert!((explorer.job.viewport.width() - 2.0).abs() < 1e-12);
+    explorer.handle(Key::Right).unwrap();
+    assert_close(explorer.job.viewport.center(), Complex { re: 0.0, im: 0.6 });
+    explorer.handle(Key::Char('-')).unwrap();
+    assert_eq!(explorer.job.viewport.rotation, 90.0);
+    assert!(explorer.job.viewport.arguments().ends_with("--rotation 90"));
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use mandelbrot::mechanism::{Registry, RenderJob};
+use mandelbrot::tiles::{
+    dzi_path, level_bounds, max_level, max_zoom, tiles, write_tiles, TILE_SIZE,
+};
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+use std::fs;
+use std::sync::Mutex;
+
+fn job() -> RenderJob {
+    let viewport =
+        Viewport::from_corners(Complex { re: -2.0, im: 1.0 }, Complex { re: 1.0, im: -0.5 });
+    RenderJob {
+        rows_per_band: 1,
+        ..common::job((600, 300), viewport)
+    }
+}
+
+#[test]
+fn test_levels() {
+    assert_eq!(max_level((1, 1)), 0);
+    assert_eq!(max_level((256, 100)), 8);
+    assert_eq!(max_level((600, 300)), 10);
+    assert_eq!(level_bounds((600, 300), 10), (600, 300));
+    assert_eq!(level_bounds((600, 300), 9), (300, 150));
+    assert_eq!(level_bounds((600, 300), 8), (150, 75));
+    assert_eq!(level_bounds((600, 300), 0), (1, 1));
+    assert_eq!(max_zoom((1, 1)), 0);
+    assert_eq!(max_zoom((256, 100)), 0);
+    assert_eq!(max_zoom((600, 300)), 2);
+}
+
+#[test]
+fn test_tiles_cover_levels() {
+    let tiles = tiles(&job());
+    //Levels 0 to 8 fit into one tile, level 9 has 2x1 and level 10 3x2 tiles. Levels 8 to 10
+    //are the XYZ zooms 0 to 2, all their tiles but the two whole ones at level 10 are computed
+    //once more as whole tiles for XYZ.
+    assert_eq!(tiles.len(), 9 + 2 + 6 + 1 + 2 + 4);
+    assert_eq!(tiles.iter().filter(|t| t.paths.len() == 2).count(), 2);
+    let last = &tiles[tiles.len() - 2];
+    assert_eq!((last.level, last.x, last.y), (10, 2, 1));
+    assert_eq!(last.paths, vec![dzi_path(10, 2, 1)]);
+    assert_eq!(last.job.bounds, (600 - 2 * TILE_SIZE, 300 - TILE_SIZE));
+    assert_eq!(last.job.viewport.lower_right, job().viewport.lower_right);
+    //The XYZ tile shows the same pixels and continues beyond the edge.
+    let xyz = tiles.last().unwrap();
+    assert_eq!(xyz.job.bounds, (TILE_SIZE, TILE_SIZE));
+    assert_eq!(xyz.job.viewport.upper_left, last.job.viewport.upper_left);
+}
+
+#[test]
+fn test_compute_units() {
+    let units: Vec<RenderJob> = tiles(&job()).into_iter().map(|t| t.job).collect();
+    let registry = Registry::default();
+    for mechanism in registry.select("all").unwrap() {
+        let done = Mutex::new(vec![false; units.len()]);
+        mechanism
+            .compute_units(&units, 3, &|index, pixels| {
+                assert!(pixels == units[index].render().unwrap());
+                done.lock().unwrap()[index] = true;
+                Ok(())
+            })
+            .unwrap();
+        assert!(
+            done.into_inner().unwrap().iter().all(|d| *d),
+            "{} missed a unit",
+            mechanism.name()
+        );
+    }
+}
+
+//Returns the value of an attribute of the Deep Zoom descriptor.
+fn attribute(dzi: &str, name: &str) -> String {
+    let start = dzi.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+    dzi[start..start + dzi[start..].find('"').unwrap()].to_string()
+}
+
+#[test]
+fn test_write_tiles() {
+    let dir = std::env::temp_dir().join("mandelbrot_test_tiles");
+    let registry = Registry::default();
+    let mechanism = registry.select("cb").unwrap()[0];
+    write_tiles(mechanism, &job(), &dir.to_string_lossy(), None).unwrap();
+
+    //Every tile is found where a Deep Zoom viewer looks for it: the levels go up to the
+    //smallest power of two covering the image and each halves the size of the next one.
+    let dzi = fs::read_to_string(dir.join("tiles.dzi")).unwrap();
+    let number = |name| attribute(&dzi, name).parse::<usize>().unwrap();
+    let (width, height, size) = (number("Width"), number("Height"), number("TileSize"));
+    assert_eq!((width, height, size), (600, 300, 256));
+    assert_eq!(number("Overlap"), 0);
+    let format = attribute(&dzi, "Format");
+    let levels = (width.max(height) as f64).log2().ceil() as u32;
+    let mut count = 0;
+    for level in 0..=levels {
+        let scale = (1 << (levels - level)) as f64;
+        let level_width = (width as f64 / scale).ceil() as usize;
+        let level_height = (height as f64 / scale).ceil() as usize;
+        for y in 0..level_height.div_ceil(size) {
+            for x in 0..level_width.div_ceil(size) {
+                let path = dir
+                    .join("tiles_files")
+                    .join(level.to_string())
+                    .join(format!("{}_{}.{}", x, y, format));
+                let tile = image::open(&path).unwrap().into_luma8();
+                let expected = (
+                    size.min(level_width - x * size),
+                    size.min(level_height - y * size),
+                );
+                assert_eq!(
+                    tile.dimensions(),
+                    (expected.0 as u32, expected.1 as u32),
+                    "{:?}",
+                    path
+                );
+                count += 1;
+            }
+        }
+    }
+    let tiles = tiles(&job());
+    assert_eq!(
+        count,
+        tiles
+            .iter()
+            .filter(|t| t.paths[0].starts_with("tiles_files"))
+            .count()
+    );
+
+    //The XYZ pyramid starts with the image in one tile and doubles it with every zoom. All its
+    //tiles are whole.
+    let zooms = max_zoom((width, height));
+    for zoom in 0..=zooms {
+        let scale = (1 << (zooms - zoom)) as f64;
+        let columns = (width as f64 / scale / size as f64).ceil() as usize;
+        let rows = (height as f64 / scale / size as f64).ceil() as usize;
+        for x in 0..columns {
+            for y in 0..rows {
+                let path = dir.join(format!("{}/{}/{}.png", zoom, x, y));
+                let tile = image::open(&path).unwrap().into_luma8();
+                assert_eq!(tile.dimensions(), (size as u32, size as u32), "{:?}", path);
+            }
+        }
+        assert!(!dir.join(format!("{}/{}/0.png", zoom, columns)).exists());
+    }
+    //A whole tile is the same in both layouts.
+    assert_eq!(
+        fs::read(dir.join("2/0/0.png")).unwrap(),
+        fs::read(dir.join("tiles_files/10/0_0.png")).unwrap()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
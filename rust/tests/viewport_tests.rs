@@ -0,0 +1,98 @@
+mod common;
+
+use common::assert_close;
+use mandelbrot::viewport::Viewport;
+use num::Complex;
+
+#[test]
+fn test_from_center() {
+    let viewport = Viewport::from_center(Complex { re: -0.5, im: 0.25 }, 2.0, (300, 200));
+    assert_eq!(viewport.upper_left, Complex { re: -2.0, im: 1.25 });
+    assert_eq!(viewport.lower_right, Complex { re: 1.0, im: -0.75 });
+    assert_eq!(viewport.center(), Complex { re: -0.5, im: 0.25 });
+    assert_eq!(viewport.zoom(), 2.0);
+}
+
+#[test]
+fn test_parse_corners() {
+    let viewport = Viewport::parse_corners("-1.6,1.2,0.6,-1.2").unwrap();
+    assert_eq!(viewport.upper_left, Complex { re: -1.6, im: 1.2 });
+    assert_eq!(viewport.lower_right, Complex { re: 0.6, im: -1.2 });
+    //The leading comma of older versions is still accepted.
+    assert_eq!(
+        Viewport::parse_corners(",-1.6,1.2,0.6,-1.2").unwrap(),
+        viewport
+    );
+    assert_eq!(viewport.complexcoords(), "-1.6,1.2,0.6,-1.2");
+    assert!(Viewport::parse_corners("-1.6,1.2,0.6").is_err());
+}
+
+#[test]
+fn test_square() {
+    let viewport =
+        Viewport::from_corners(Complex { re: -2.0, im: 1.0 }, Complex { re: 2.0, im: -1.0 });
+    //A wider image grows the width, a taller one the height.
+    let wide = viewport.square((400, 100));
+    assert_close(wide.upper_left, Complex { re: -4.0, im: 1.0 });
+    assert_close(wide.lower_right, Complex { re: 4.0, im: -1.0 });
+    let tall = viewport.square((100, 100));
+    assert_close(tall.upper_left, Complex { re: -2.0, im: 2.0 });
+    assert_close(tall.lower_right, Complex { re: 2.0, im: -2.0 });
+    assert_eq!(viewport.square((200, 100)), viewport);
+}
+
+#[test]
+fn test_region() {
+    let viewport =
+        Viewport::from_corners(Complex { re: -2.0, im: 2.0 }, Complex { re: 2.0, im: -2.0 });
+    let region = viewport.region((64, 64), (16, 8), (32, 16));
+    assert_eq!(region.upper_left, Complex { re: -1.0, im: 1.5 });
+    assert_eq!(region.lower_right, Complex { re: 1.0, im: 0.5 });
+    assert_eq!(
+        region.pixel_to_point((32, 16), (3, 5)),
+        viewport.pixel_to_point((64, 64), (19, 13))
+    );
+}
+
+#[test]
+fn test_rotation() {
+    let viewport =
+        Viewport::from_corners(Complex { re: -2.0, im: 1.0 }, Complex { re: 2.0, im: -1.0 })
+            .rotate(90.0);
+    //The upper left corner turns to the lower left.
+    assert_close(
+        viewport.pixel_to_point((40, 20), (0, 0)),
+        Complex { re: -1.0, im: -2.0 },
+    );
+    assert_close(
+        viewport.pixel_to_point((40, 20), (20, 10)),
+        Complex { re: 0.0, im: 0.0 },
+    );
+    assert_close(
+        viewport.pixel_to_point((40, 20), (30, 10)),
+        Complex { re: 0.0, im: 1.0 },
+    );
+    assert_eq!(viewport.square((40, 20)).rotation, 90.0);
+    assert_eq!(
+        viewport.rotate(-60.0).arguments(),
+        "--complexcoords -2,1,2,-1 --rotation 30"
+    );
+}
+
+#[test]
+fn test_rotated_region() {
+    let viewport =
+        Viewport::from_corners(Complex { re: -1.6, im: 1.2 }, Complex { re: 0.6, im: -1.2 })
+            .rotate(33.0);
+    let bounds = (50, 40);
+    let region = viewport.region(bounds, (10, 25), (30, 15));
+    let transform = region.transform((30, 15));
+    for row in 0..15 {
+        for column in 0..30 {
+            assert_close(
+                transform.pixel_to_point((column, row)),
+                viewport.pixel_to_point(bounds, (column + 10, row + 25)),
+            );
+        }
+    }
+}